@@ -1,6 +1,13 @@
+#![allow(clippy::needless_return)]
+
 use std::env;
 use std::cmp::max;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fs;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -10,45 +17,77 @@ fn main() {
     } else if args[1] == "--solve-b" {
         let solution = solve_b();
         println!("{}", solution)
+    } else if args[1] == "--solve-streaming" {
+        // --solve-streaming K [FILE] - reads from stdin when FILE is missing or "-"
+        let k = args[2].parse::<usize>().expect("K must be a non-negative integer");
+        let result = match args.get(3) {
+            Some(path) if path != "-" => {
+                let file = fs::File::open(path).expect("Something went wrong opening the file");
+                stream_top_k_total(BufReader::new(file), k)
+            },
+            _ => stream_top_k_total(io::stdin().lock(), k)
+        };
+        match result {
+            Ok(val) => println!("{}", val),
+            Err(e) => panic!("{}", e)
+        }
+    } else if args[1] == "--check-streaming" {
+        match check_streaming() {
+            Ok(count) => println!("Streaming solver matched in-memory solver on {} generated inputs", count),
+            Err(e) => panic!("{}", e)
+        }
+    } else if args[1] == "--redistribute" {
+        let plan = redistribute(&parse_elves(&load_input()));
+        print!("{}", plan)
     } else {
-        println!("Pass either --solve-a or --solve-b")
+        println!("Pass either --solve-a, --solve-b, --solve-streaming K [FILE], --check-streaming or --redistribute")
     }
 }
 
 fn solve_a() -> i32 {
     let calories = load_input();
+    return max_calories(&calories);
+}
+
+fn solve_b() -> i32 {
+    let calories = load_input();
+    return top_three_calories(&calories);
+}
 
+fn max_calories(calories: &[i32]) -> i32 {
     let mut current_calories = 0;
     let mut max_calories = 0;
 
     for calorie in calories {
-        if calorie == -1 {
+        if *calorie == -1 {
             // Reached end of current elf's inventory
             max_calories = max(current_calories, max_calories);
             current_calories = 0;
         } else {
-            current_calories = current_calories + calorie
+            current_calories += calorie
         }
     }
+    // The last elf's inventory may not be followed by a blank line
+    max_calories = max(current_calories, max_calories);
 
     return max_calories;
 }
 
-fn solve_b() -> i32 {
-    let calories = load_input();
-
+fn top_three_calories(calories: &[i32]) -> i32 {
     let mut calorie_totals: Vec<i32> = Vec::new();
     let mut current_calories = 0;
 
     for calorie in calories {
-        if calorie == -1 {
+        if *calorie == -1 {
             // Reached end of current elf's inventory
             calorie_totals.push(current_calories);
             current_calories = 0;
         } else {
-            current_calories = current_calories + calorie
+            current_calories += calorie
         }
     }
+    // The last elf's inventory may not be followed by a blank line
+    calorie_totals.push(current_calories);
 
     let mut max_1 = 0;
     let mut max_2 = 0;
@@ -69,6 +108,322 @@ fn solve_b() -> i32 {
     return max_1 + max_2 + max_3;
 }
 
+// Sums the calories carried by the k best-stocked elves, reading one line at a time.
+// Only the current elf's total and a min-heap of the best k totals are kept in memory,
+// so the input can be far larger than RAM.
+fn stream_top_k_total<R: BufRead>(mut reader: R, k: usize) -> Result<u64, String> {
+    let mut top_totals: BinaryHeap<Reverse<u64>> = BinaryHeap::with_capacity(k + 1);
+    let mut current_calories: u64 = 0;
+    let mut elf_has_items = false;
+    let mut line = String::new();
+    let mut line_number = 0;
+
+    loop {
+        line.clear();
+        let bytes_read = match reader.read_line(&mut line) {
+            Ok(x) => x,
+            Err(e) => return Err(e.to_string())
+        };
+        line_number += 1;
+        let item = line.trim_end_matches(['\n', '\r']);
+
+        if bytes_read == 0 || item.is_empty() {
+            // Reached end of current elf's inventory
+            if elf_has_items {
+                top_totals.push(Reverse(current_calories));
+                if top_totals.len() > k {
+                    top_totals.pop();
+                }
+            }
+            current_calories = 0;
+            elf_has_items = false;
+            if bytes_read == 0 {
+                break;
+            }
+            continue;
+        }
+
+        let calorie = match item.parse::<u64>() {
+            Ok(x) => x,
+            Err(_) => return Err(format!("Line {}: could not parse '{}' as calories", line_number, item))
+        };
+        current_calories = match current_calories.checked_add(calorie) {
+            Some(x) => x,
+            None => return Err(format!("Line {}: elf's calorie total overflowed", line_number))
+        };
+        elf_has_items = true;
+    }
+
+    return Ok(top_totals.iter().map(|total| total.0).sum());
+}
+
+// Seeded generator for the inputs check_streaming makes up
+struct XorShift {
+    state: u64
+}
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        return self.state;
+    }
+
+    fn below(&mut self, limit: u64) -> u64 {
+        return self.next() % limit;
+    }
+}
+
+// Runs the streaming solver and the in-memory solvers over a batch of generated inputs and
+// fails on the first disagreement. Returns how many inputs were checked.
+fn check_streaming() -> Result<usize, String> {
+    let mut rng = XorShift{state: 0x2022_1201};
+    let input_count = 500;
+
+    for input_index in 0..input_count {
+        let elf_count = 1 + rng.below(200);
+        let mut input = String::new();
+        let mut calories: Vec<i32> = Vec::new();
+        for elf in 0..elf_count {
+            if elf > 0 {
+                input.push('\n');
+                calories.push(-1);
+            }
+            for _item in 0..(1 + rng.below(15)) {
+                let calorie = 1 + rng.below(70000) as i32;
+                input.push_str(&calorie.to_string());
+                input.push('\n');
+                calories.push(calorie);
+            }
+        }
+        // Exercise both files that end with a blank line and files that don't
+        if rng.below(2) == 0 {
+            input.push('\n');
+            calories.push(-1);
+        }
+
+        let expected_a = max_calories(&calories) as u64;
+        let actual_a = stream_top_k_total(input.as_bytes(), 1)?;
+        if expected_a != actual_a {
+            return Err(format!("Input {}: in-memory max was {} but streaming gave {}", input_index, expected_a, actual_a));
+        }
+
+        let expected_b = top_three_calories(&calories) as u64;
+        let actual_b = stream_top_k_total(input.as_bytes(), 3)?;
+        if expected_b != actual_b {
+            return Err(format!("Input {}: in-memory top three was {} but streaming gave {}", input_index, expected_b, actual_b));
+        }
+    }
+
+    return Ok(input_count);
+}
+
+// Above this many items the exact search gets too slow, so we fall back to the LPT heuristic. At 16 the worst
+// case is a few milliseconds, while 24 items can take minutes.
+const EXACT_SOLVER_MAX_ITEMS: usize = 16;
+
+#[derive(Clone, Copy)]
+struct SnackItem {
+    owner: usize,
+    calories: i64
+}
+
+struct SnackMove {
+    calories: i64,
+    from_elf: usize,
+    to_elf: usize
+}
+
+struct RedistributionPlan {
+    moves: Vec<SnackMove>,
+    elf_totals: Vec<i64>,
+    exact: bool
+}
+
+impl RedistributionPlan {
+    fn spread(&self) -> i64 {
+        let max_total = self.elf_totals.iter().max().copied().unwrap_or(0);
+        let min_total = self.elf_totals.iter().min().copied().unwrap_or(0);
+        return max_total - min_total;
+    }
+}
+
+impl std::fmt::Display for RedistributionPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for snack_move in &self.moves {
+            writeln!(f, "move {} from elf {} to elf {}", snack_move.calories, snack_move.from_elf + 1, snack_move.to_elf + 1)?;
+        }
+        let max_total = self.elf_totals.iter().max().copied().unwrap_or(0);
+        let min_total = self.elf_totals.iter().min().copied().unwrap_or(0);
+        writeln!(f, "{} items moved ({})", self.moves.len(), if self.exact { "exact" } else { "LPT heuristic" })?;
+        writeln!(f, "richest elf: {}, poorest elf: {}, spread: {}", max_total, min_total, self.spread())
+    }
+}
+
+fn parse_elves(calories: &[i32]) -> Vec<Vec<i32>> {
+    let mut elves: Vec<Vec<i32>> = Vec::new();
+    let mut current_elf: Vec<i32> = Vec::new();
+    for calorie in calories {
+        if *calorie == -1 {
+            // Reached end of current elf's inventory
+            elves.push(current_elf);
+            current_elf = Vec::new();
+        } else {
+            current_elf.push(*calorie);
+        }
+    }
+    if !current_elf.is_empty() {
+        elves.push(current_elf);
+    }
+
+    return elves;
+}
+
+// Moves whole items between elves so that the richest elf ends up carrying as little as possible
+fn redistribute(elves: &[Vec<i32>]) -> RedistributionPlan {
+    let elf_count = elves.len();
+    let mut items: Vec<SnackItem> = Vec::new();
+    for (owner, elf) in elves.iter().enumerate() {
+        for calorie in elf {
+            items.push(SnackItem{owner, calories: *calorie as i64});
+        }
+    }
+    // Both solvers want the biggest items placed first
+    items.sort_by_key(|item| Reverse(item.calories));
+
+    let sizes: Vec<i64> = items.iter().map(|item| item.calories).collect();
+    let exact = items.len() <= EXACT_SOLVER_MAX_ITEMS;
+    let bins = if exact { partition_exact(&sizes, elf_count) } else { partition_lpt(&sizes, elf_count) };
+
+    // The partition doesn't care which elf gets which bin, so hand each bin to the elf that already
+    // holds most of its items to keep the number of moves down
+    let elf_for_bin = assign_bins_to_elves(&items, &bins, elf_count);
+
+    let mut moves: Vec<SnackMove> = Vec::new();
+    let mut elf_totals: Vec<i64> = vec![0; elf_count];
+    for (item, bin) in items.iter().zip(&bins) {
+        let to_elf = elf_for_bin[*bin];
+        elf_totals[to_elf] += item.calories;
+        if to_elf != item.owner {
+            moves.push(SnackMove{calories: item.calories, from_elf: item.owner, to_elf});
+        }
+    }
+    moves.sort_by_key(|snack_move| (snack_move.from_elf, snack_move.to_elf));
+
+    return RedistributionPlan{moves, elf_totals, exact};
+}
+
+// Longest-processing-time-first: give each item, biggest first, to the currently lightest bin.
+// Expects sizes sorted in descending order and returns the bin chosen for each item.
+fn partition_lpt(sizes: &[i64], bin_count: usize) -> Vec<usize> {
+    let mut loads: BinaryHeap<Reverse<(i64, usize)>> = (0..bin_count).map(|bin| Reverse((0, bin))).collect();
+    let mut bins: Vec<usize> = Vec::with_capacity(sizes.len());
+    for size in sizes {
+        let Reverse((load, bin)) = loads.pop().unwrap();
+        bins.push(bin);
+        loads.push(Reverse((load + size, bin)));
+    }
+
+    return bins;
+}
+
+// Branch and bound over every assignment of items to bins, seeded with the LPT answer.
+// Expects sizes sorted in descending order and returns the bin chosen for each item.
+fn partition_exact(sizes: &[i64], bin_count: usize) -> Vec<usize> {
+    let mut best_bins = partition_lpt(sizes, bin_count);
+    if sizes.is_empty() || bin_count <= 1 {
+        return best_bins;
+    }
+
+    let mut best_max = max_load(sizes, &best_bins, bin_count);
+    // No partition can beat a perfect split, nor split the biggest item
+    let total: i64 = sizes.iter().sum();
+    let lower_bound = max((total + bin_count as i64 - 1) / bin_count as i64, sizes[0]);
+
+    let mut loads: Vec<i64> = vec![0; bin_count];
+    let mut bins: Vec<usize> = vec![0; sizes.len()];
+    search_partitions(sizes, 0, lower_bound, &mut loads, &mut bins, &mut best_max, &mut best_bins);
+
+    return best_bins;
+}
+
+fn search_partitions(
+    sizes: &[i64],
+    item: usize,
+    lower_bound: i64,
+    loads: &mut Vec<i64>,
+    bins: &mut Vec<usize>,
+    best_max: &mut i64,
+    best_bins: &mut Vec<usize>
+) {
+    if *best_max == lower_bound {
+        // Can't do any better than this
+        return;
+    }
+    if item == sizes.len() {
+        let this_max = loads.iter().copied().max().unwrap();
+        if this_max < *best_max {
+            *best_max = this_max;
+            best_bins.clone_from(bins);
+        }
+        return;
+    }
+
+    let mut tried_loads: Vec<i64> = Vec::new();
+    for bin in 0..loads.len() {
+        // Bins with the same load are interchangeable, so only try one of them
+        if tried_loads.contains(&loads[bin]) {
+            continue;
+        }
+        tried_loads.push(loads[bin]);
+        if loads[bin] + sizes[item] >= *best_max {
+            continue;
+        }
+
+        loads[bin] += sizes[item];
+        bins[item] = bin;
+        search_partitions(sizes, item + 1, lower_bound, loads, bins, best_max, best_bins);
+        loads[bin] -= sizes[item];
+    }
+}
+
+fn max_load(sizes: &[i64], bins: &[usize], bin_count: usize) -> i64 {
+    let mut loads: Vec<i64> = vec![0; bin_count];
+    for (size, bin) in sizes.iter().zip(bins) {
+        loads[*bin] += size;
+    }
+
+    return loads.into_iter().max().unwrap_or(0);
+}
+
+// Greedily pairs bins with elves, largest number of already-held items first
+fn assign_bins_to_elves(items: &[SnackItem], bins: &[usize], elf_count: usize) -> Vec<usize> {
+    let mut overlap: Vec<Vec<usize>> = vec![vec![0; elf_count]; elf_count];
+    for (item, bin) in items.iter().zip(bins) {
+        overlap[*bin][item.owner] += 1;
+    }
+
+    let mut pairs: Vec<(usize, usize, usize)> = Vec::new();
+    for (bin, row) in overlap.iter().enumerate() {
+        for (elf, count) in row.iter().enumerate() {
+            pairs.push((*count, bin, elf));
+        }
+    }
+    pairs.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+    let mut elf_for_bin: Vec<Option<usize>> = vec![None; elf_count];
+    let mut elf_taken: Vec<bool> = vec![false; elf_count];
+    for (_count, bin, elf) in pairs {
+        if elf_for_bin[bin].is_none() && !elf_taken[elf] {
+            elf_for_bin[bin] = Some(elf);
+            elf_taken[elf] = true;
+        }
+    }
+
+    return elf_for_bin.into_iter().map(|elf| elf.unwrap()).collect();
+}
+
 fn is_example_mode() -> bool {
     let example_mode = env::var("AOC_EXAMPLE_MODE");
     if example_mode.is_err() {
@@ -88,7 +443,7 @@ fn load_input() -> Vec<i32> {
 
     let mut output: Vec<i32> = Vec::new();
     for line in contents.lines() {
-        if line == "" {
+        if line.is_empty() {
             output.push(-1);
         } else {
             output.push(line.parse::<i32>().unwrap());
@@ -97,3 +452,64 @@ fn load_input() -> Vec<i32> {
 
     return output;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = include_str!("../../exampleInput.txt");
+
+    #[test]
+    fn streaming_matches_in_memory_on_generated_inputs() {
+        assert_eq!(check_streaming(), Ok(500));
+    }
+
+    #[test]
+    fn streaming_solves_example() {
+        assert_eq!(stream_top_k_total(EXAMPLE.as_bytes(), 1), Ok(24000));
+        assert_eq!(stream_top_k_total(EXAMPLE.as_bytes(), 3), Ok(45000));
+    }
+
+    #[test]
+    fn streaming_handles_line_endings() {
+        assert_eq!(stream_top_k_total("1\r\n2\r\n\r\n4\r\n".as_bytes(), 1), Ok(4));
+        assert_eq!(stream_top_k_total("1\n2\n\n4".as_bytes(), 2), Ok(7));
+        assert_eq!(stream_top_k_total("".as_bytes(), 3), Ok(0));
+    }
+
+    // Tries every assignment of items to bins, for checking the exact solver on small inputs
+    fn brute_force_max_load(sizes: &[i64], bin_count: usize) -> i64 {
+        let mut best = i64::MAX;
+        let mut bins: Vec<usize> = vec![0; sizes.len()];
+        for assignment in 0..bin_count.pow(sizes.len() as u32) {
+            let mut rest = assignment;
+            for bin in bins.iter_mut() {
+                *bin = rest % bin_count;
+                rest /= bin_count;
+            }
+            best = best.min(max_load(sizes, &bins, bin_count));
+        }
+        return best;
+    }
+
+    #[test]
+    fn exact_partition_matches_brute_force() {
+        let mut rng = XorShift{state: 0x2022_1201};
+        for _input in 0..300 {
+            let bin_count = 1 + rng.below(4) as usize;
+            let mut sizes: Vec<i64> = (0..rng.below(9)).map(|_| 1 + rng.below(50) as i64).collect();
+            sizes.sort_by_key(|size| Reverse(*size));
+            let bins = partition_exact(&sizes, bin_count);
+            assert_eq!(bins.len(), sizes.len());
+            assert_eq!(max_load(&sizes, &bins, bin_count), brute_force_max_load(&sizes, bin_count), "sizes {:?} into {} bins", sizes, bin_count);
+        }
+    }
+
+    #[test]
+    fn streaming_reports_bad_lines() {
+        assert_eq!(
+            stream_top_k_total("1\n\nabc\n".as_bytes(), 1),
+            Err("Line 3: could not parse 'abc' as calories".to_string())
+        );
+    }
+}
@@ -1,25 +1,60 @@
+#![allow(clippy::needless_return)]
+
 use std::env;
 use std::fs;
+use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    let rules = match load_rules(get_option(&args, "--rules")) {
+        Ok(x) => x,
+        Err(e) => panic!("{}", e)
+    };
+
     if args[1] == "--solve-a" {
-        let solution = solve_a();
-        println!("{}", solution)
+        match solve_a(&rules) {
+            Ok(val) => println!("{}", val),
+            Err(e) => panic!("{}", e)
+        }
     } else if args[1] == "--solve-b" {
-        let solution = solve_b();
-        println!("{}", solution)
+        match solve_b(&rules) {
+            Ok(val) => println!("{}", val),
+            Err(e) => panic!("{}", e)
+        }
+    } else if args[1] == "--show-rules" {
+        print!("{}", rules.describe())
+    } else if args[1] == "--decode-search" {
+        match decode_search(&rules) {
+            Ok(report) => print!("{}", report),
+            Err(e) => panic!("{}", e)
+        }
+    } else if args[1] == "--tournament" {
+        let rounds = get_option(&args, "--rounds").map_or(1000, |x| x.parse::<usize>().expect("--rounds must be a number"));
+        let seed = get_option(&args, "--seed").map_or(1, |x| x.parse::<u64>().expect("--seed must be a number"));
+        match run_tournament(&rules, rounds, seed) {
+            Ok(report) => print!("{}", report),
+            Err(e) => panic!("{}", e)
+        }
+    } else if args[1] == "--expected-score" {
+        let simulated_rounds = get_option(&args, "--simulate").map_or(0, |x| x.parse::<usize>().expect("--simulate must be a number"));
+        let seed = get_option(&args, "--seed").map_or(1, |x| x.parse::<u64>().expect("--seed must be a number"));
+        match expected_scores(&rules, get_option(&args, "--distribution").unwrap_or("guide"), simulated_rounds, seed) {
+            Ok(report) => print!("{}", report),
+            Err(e) => panic!("{}", e)
+        }
     } else {
-        println!("Pass either --solve-a or --solve-b")
+        println!("Pass either --solve-a, --solve-b, --show-rules, --decode-search, --tournament [--rounds N] [--seed S] or --expected-score [--distribution guide|W:W:...] [--simulate N] [--seed S], optionally followed by --rules <classic|rpsls|cyclic:N|FILE>")
     }
 }
 
-#[derive(PartialEq, Copy, Clone)]
-enum Move {
-    Rock,
-    Paper,
-    Scissors
-}
+type ResultOrErr<X> = Result<X, String>;
+
+// Index into the rule table's list of moves
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
+struct Move(usize);
 
 struct Game {
     my_move: Move,
@@ -32,81 +67,1148 @@ struct GamePlan {
     opponent_move: Move
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
 enum Outcome {
     Win,
     Draw,
     Lose
 }
 
-fn score_game(game: Game) -> i32 {
-    let move_score = match game.my_move {
-        Move::Rock => 1,
-        Move::Paper => 2,
-        Move::Scissors => 3
+struct MoveRule {
+    name: String,
+    shape_score: i32,
+    opponent_symbol: char,
+    response_symbol: char
+}
+
+struct RuleTable {
+    moves: Vec<MoveRule>,
+    // beats[a][b] is true when move a beats move b. Neither beating the other is a draw.
+    beats: Vec<Vec<bool>>,
+    win_score: i32,
+    draw_score: i32,
+    lose_score: i32,
+    outcome_symbols: HashMap<char, Outcome>
+}
+
+const CLASSIC_RULES: &str = "\
+move Rock 1 A X
+move Paper 2 B Y
+move Scissors 3 C Z
+beats Rock Scissors
+beats Paper Rock
+beats Scissors Paper
+";
+
+const RPSLS_RULES: &str = "\
+move Rock 1 A X
+move Paper 2 B Y
+move Scissors 3 C Z
+move Lizard 4 D W
+move Spock 5 E V
+beats Rock Scissors Lizard
+beats Paper Rock Spock
+beats Scissors Paper Lizard
+beats Lizard Paper Spock
+beats Spock Rock Scissors
+";
+
+// Accepts a preset name or the path of a rules file. No name means the puzzle's own rules.
+fn load_rules(name: Option<&str>) -> ResultOrErr<RuleTable> {
+    return match name {
+        None | Some("classic") => RuleTable::parse(CLASSIC_RULES),
+        Some("rpsls") => RuleTable::parse(RPSLS_RULES),
+        Some(preset) if preset.starts_with("cyclic:") => {
+            let move_count = match preset["cyclic:".len()..].parse::<usize>() {
+                Ok(x) => x,
+                Err(_) => return Err(format!("Could not parse move count in '{}'", preset))
+            };
+            RuleTable::cyclic(move_count)
+        },
+        Some(path) => {
+            let contents = match fs::read_to_string(path) {
+                Ok(x) => x,
+                Err(x) => return Err(x.to_string())
+            };
+            RuleTable::parse(&contents)
+        }
+    };
+}
+
+impl RuleTable {
+    // Rules files are made of lines like:
+    //   move <name> <shape score> <opponent symbol> <response symbol>
+    //   beats <name> <beaten name>...
+    //   score <win|draw|lose> <points>
+    //   outcome <symbol> <win|draw|lose>
+    // Scores and outcome symbols default to the puzzle's. Blank lines and lines starting with # are ignored.
+    fn parse(input: &str) -> ResultOrErr<RuleTable> {
+        let mut rules = RuleTable{
+            moves: Vec::new(),
+            beats: Vec::new(),
+            win_score: 6,
+            draw_score: 3,
+            lose_score: 0,
+            outcome_symbols: HashMap::from([('X', Outcome::Lose), ('Y', Outcome::Draw), ('Z', Outcome::Win)])
+        };
+        let mut beats_lines: Vec<(usize, Vec<&str>)> = Vec::new();
+        let mut custom_outcome_symbols = false;
+
+        for (index, line) in input.lines().enumerate() {
+            let line_number = index + 1;
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.is_empty() || parts[0].starts_with('#') {
+                continue
+            }
+            match parts[0] {
+                "move" => {
+                    if parts.len() != 5 {
+                        return Err(format!("Line {}: expected 'move <name> <shape score> <opponent symbol> <response symbol>'", line_number));
+                    }
+                    let shape_score = match parts[2].parse::<i32>() {
+                        Ok(x) => x,
+                        Err(_) => return Err(format!("Line {}: could not parse shape score '{}'", line_number, parts[2]))
+                    };
+                    let rule = MoveRule{
+                        name: parts[1].to_string(),
+                        shape_score,
+                        opponent_symbol: parse_symbol(parts[3], line_number)?,
+                        response_symbol: parse_symbol(parts[4], line_number)?
+                    };
+                    for earlier in &rules.moves {
+                        if let Err(e) = check_distinct(earlier, &rule) {
+                            return Err(format!("Line {}: {}", line_number, e));
+                        }
+                    }
+                    rules.moves.push(rule);
+                },
+                "beats" => {
+                    if parts.len() < 3 {
+                        return Err(format!("Line {}: expected 'beats <name> <beaten name>...'", line_number));
+                    }
+                    // Moves may be declared after the lines that use them, so resolve these at the end
+                    beats_lines.push((line_number, parts[1..].to_vec()));
+                },
+                "score" => {
+                    if parts.len() != 3 {
+                        return Err(format!("Line {}: expected 'score <win|draw|lose> <points>'", line_number));
+                    }
+                    let points = match parts[2].parse::<i32>() {
+                        Ok(x) => x,
+                        Err(_) => return Err(format!("Line {}: could not parse points '{}'", line_number, parts[2]))
+                    };
+                    match parse_outcome(parts[1], line_number)? {
+                        Outcome::Win => rules.win_score = points,
+                        Outcome::Draw => rules.draw_score = points,
+                        Outcome::Lose => rules.lose_score = points
+                    }
+                },
+                "outcome" => {
+                    if parts.len() != 3 {
+                        return Err(format!("Line {}: expected 'outcome <symbol> <win|draw|lose>'", line_number));
+                    }
+                    if !custom_outcome_symbols {
+                        rules.outcome_symbols.clear();
+                        custom_outcome_symbols = true;
+                    }
+                    let symbol = parse_symbol(parts[1], line_number)?;
+                    if rules.outcome_symbols.insert(symbol, parse_outcome(parts[2], line_number)?).is_some() {
+                        return Err(format!("Line {}: outcome symbol '{}' is used twice", line_number, symbol));
+                    }
+                },
+                _ => return Err(format!("Line {}: unknown rule '{}'", line_number, parts[0]))
+            }
+        }
+
+        rules.beats = vec![vec![false; rules.moves.len()]; rules.moves.len()];
+        for (line_number, names) in beats_lines {
+            let winner = rules.find_move_by_name(names[0], line_number)?;
+            for name in &names[1..] {
+                let loser = rules.find_move_by_name(name, line_number)?;
+                if winner == loser {
+                    return Err(format!("Line {}: move '{}' can't beat itself", line_number, name));
+                }
+                if rules.beats[loser.0][winner.0] {
+                    return Err(format!("Line {}: moves '{}' and '{}' can't both beat each other", line_number, names[0], name));
+                }
+                rules.beats[winner.0][loser.0] = true;
+            }
+        }
+
+        rules.validate()?;
+        return Ok(rules);
+    }
+
+    // An odd number of moves arranged in a circle, where each move beats the half of the circle behind it.
+    // With three moves this is the puzzle's game.
+    fn cyclic(move_count: usize) -> ResultOrErr<RuleTable> {
+        if move_count < 3 || move_count.is_multiple_of(2) || move_count > 13 {
+            return Err(format!("Cyclic games need an odd number of moves between 3 and 13, got {}", move_count));
+        }
+
+        let mut rules = RuleTable{
+            moves: Vec::new(),
+            beats: vec![vec![false; move_count]; move_count],
+            win_score: 6,
+            draw_score: 3,
+            lose_score: 0,
+            outcome_symbols: HashMap::from([('X', Outcome::Lose), ('Y', Outcome::Draw), ('Z', Outcome::Win)])
+        };
+        for index in 0..move_count {
+            // Opponent symbols count up from A and response symbols end at Z, so three moves gives A/B/C and X/Y/Z
+            rules.moves.push(MoveRule{
+                name: format!("Move{}", index + 1),
+                shape_score: index as i32 + 1,
+                opponent_symbol: (b'A' + index as u8) as char,
+                response_symbol: (b'Z' + 1 + index as u8 - move_count as u8) as char
+            });
+            for distance in 1..=(move_count / 2) {
+                rules.beats[index][(index + move_count - distance) % move_count] = true;
+            }
+        }
+
+        rules.validate()?;
+        return Ok(rules);
+    }
+
+    fn validate(&self) -> ResultOrErr<()> {
+        if self.moves.is_empty() {
+            return Err("Rules must declare at least one move".to_string());
+        }
+        for (index, rule) in self.moves.iter().enumerate() {
+            for other in &self.moves[index + 1..] {
+                check_distinct(rule, other)?;
+            }
+        }
+        for a in 0..self.moves.len() {
+            if self.beats[a][a] {
+                return Err(format!("Move '{}' can't beat itself", self.moves[a].name));
+            }
+            for b in 0..self.moves.len() {
+                if self.beats[a][b] && self.beats[b][a] {
+                    return Err(format!("Moves '{}' and '{}' can't both beat each other", self.moves[a].name, self.moves[b].name));
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    fn find_move_by_name(&self, name: &str, line_number: usize) -> ResultOrErr<Move> {
+        return match self.moves.iter().position(|rule| rule.name == name) {
+            Some(index) => Ok(Move(index)),
+            None => Err(format!("Line {}: unknown move '{}'", line_number, name))
+        };
+    }
+
+    fn response_symbols(&self) -> HashMap<char, Move> {
+        return self.all_moves().map(|my_move| (self.moves[my_move.0].response_symbol, my_move)).collect();
+    }
+
+    fn opponent_move_for_symbol(&self, symbol: char) -> Option<Move> {
+        return self.moves.iter().position(|rule| rule.opponent_symbol == symbol).map(Move);
+    }
+
+    fn all_moves(&self) -> impl Iterator<Item = Move> {
+        return (0..self.moves.len()).map(Move);
+    }
+
+    fn outcome(&self, game: &Game) -> Outcome {
+        if self.beats[game.my_move.0][game.opponent_move.0] {
+            return Outcome::Win;
+        }
+        if self.beats[game.opponent_move.0][game.my_move.0] {
+            return Outcome::Lose;
+        }
+
+        return Outcome::Draw;
+    }
+
+    fn outcome_score(&self, outcome: Outcome) -> i32 {
+        return match outcome {
+            Outcome::Win => self.win_score,
+            Outcome::Draw => self.draw_score,
+            Outcome::Lose => self.lose_score
+        };
+    }
+
+    // Picks our move for a game plan. When several moves give the planned outcome, take the one
+    // with the best shape score.
+    fn response_for(&self, game_plan: &GamePlan) -> ResultOrErr<Move> {
+        let mut best: Option<Move> = None;
+        for my_move in self.all_moves() {
+            let game = Game{my_move, opponent_move: game_plan.opponent_move};
+            if self.outcome(&game) != game_plan.outcome {
+                continue
+            }
+            if best.is_none() || self.moves[my_move.0].shape_score > self.moves[best.unwrap().0].shape_score {
+                best = Some(my_move);
+            }
+        }
+
+        return match best {
+            Some(x) => Ok(x),
+            None => Err(format!("No move gives a {:?} against {}", game_plan.outcome, self.moves[game_plan.opponent_move.0].name))
+        };
+    }
+
+    fn describe(&self) -> String {
+        let mut output = String::new();
+        for (index, rule) in self.moves.iter().enumerate() {
+            let beaten: Vec<&str> = self.all_moves()
+                .filter(|other| self.beats[index][other.0])
+                .map(|other| &self.moves[other.0].name[..])
+                .collect();
+            output.push_str(&format!(
+                "{} ({}/{}, {} points) beats: {}\n",
+                rule.name, rule.opponent_symbol, rule.response_symbol, rule.shape_score, beaten.join(", ")
+            ));
+        }
+        output.push_str(&format!("win: {}, draw: {}, lose: {}\n", self.win_score, self.draw_score, self.lose_score));
+
+        return output;
+    }
+}
+
+fn check_distinct(rule: &MoveRule, other: &MoveRule) -> ResultOrErr<()> {
+    if rule.name == other.name {
+        return Err(format!("move '{}' is declared twice", rule.name));
+    }
+    if rule.opponent_symbol == other.opponent_symbol {
+        return Err(format!("moves '{}' and '{}' share opponent symbol '{}'", rule.name, other.name, rule.opponent_symbol));
+    }
+    if rule.response_symbol == other.response_symbol {
+        return Err(format!("moves '{}' and '{}' share response symbol '{}'", rule.name, other.name, rule.response_symbol));
+    }
+    return Ok(());
+}
+
+fn parse_symbol(input: &str, line_number: usize) -> ResultOrErr<char> {
+    let mut chars = input.chars();
+    return match (chars.next(), chars.next()) {
+        (Some(symbol), None) => Ok(symbol),
+        _ => Err(format!("Line {}: symbol '{}' must be a single character", line_number, input))
     };
+}
+
+fn parse_outcome(input: &str, line_number: usize) -> ResultOrErr<Outcome> {
+    return match input {
+        "win" => Ok(Outcome::Win),
+        "draw" => Ok(Outcome::Draw),
+        "lose" => Ok(Outcome::Lose),
+        _ => Err(format!("Line {}: outcome must be win, draw or lose, got '{}'", line_number, input))
+    };
+}
+
+fn score_game(rules: &RuleTable, game: Game) -> i32 {
+    let move_score = rules.moves[game.my_move.0].shape_score;
+    return move_score + rules.outcome_score(rules.outcome(&game));
+}
+
+// One line of the strategy guide, before deciding what its second column means
+struct GuideEntry {
+    line_number: usize,
+    opponent_symbol: char,
+    response_symbol: char
+}
+
+// A reading of the guide's second column that turns each entry into the game we'd play
+trait Interpretation {
+    fn to_game(&self, rules: &RuleTable, entry: &GuideEntry) -> ResultOrErr<Game>;
+    fn describe(&self, rules: &RuleTable) -> String;
+}
+
+// Part A: the second column is the move we should play
+struct MoveInterpretation {
+    response_moves: HashMap<char, Move>
+}
+
+// Part B: the second column is how the round should end
+struct OutcomeInterpretation {
+    outcomes: HashMap<char, Outcome>
+}
+
+impl MoveInterpretation {
+    fn from_rules(rules: &RuleTable) -> MoveInterpretation {
+        return MoveInterpretation{response_moves: rules.response_symbols()};
+    }
+}
 
-    if game.my_move == game.opponent_move {
-        // Draw!
-        return move_score + 3;
+impl OutcomeInterpretation {
+    fn from_rules(rules: &RuleTable) -> OutcomeInterpretation {
+        return OutcomeInterpretation{outcomes: rules.outcome_symbols.clone()};
     }
+}
 
-    // We know we have a winner - was it us?
-    if game.my_move == Move::Rock && game.opponent_move == Move::Scissors {
-        return move_score + 6
+impl Interpretation for MoveInterpretation {
+    fn to_game(&self, rules: &RuleTable, entry: &GuideEntry) -> ResultOrErr<Game> {
+        let opponent_move = opponent_move_for_entry(rules, entry)?;
+        let my_move = match self.response_moves.get(&entry.response_symbol) {
+            Some(x) => *x,
+            None => return Err(format!("Line {}: '{}' is not a move", entry.line_number, entry.response_symbol))
+        };
+
+        return Ok(Game{my_move, opponent_move});
     }
-    if game.my_move == Move::Paper && game.opponent_move == Move::Rock {
-        return move_score + 6
+
+    fn describe(&self, rules: &RuleTable) -> String {
+        let mut parts: Vec<String> = self.response_moves.iter()
+            .map(|(symbol, my_move)| format!("{}={}", symbol, rules.moves[my_move.0].name))
+            .collect();
+        parts.sort();
+        return format!("moves    {}", parts.join(" "));
     }
-    if game.my_move == Move::Scissors && game.opponent_move == Move::Paper {
-        return move_score + 6
+}
+
+impl Interpretation for OutcomeInterpretation {
+    fn to_game(&self, rules: &RuleTable, entry: &GuideEntry) -> ResultOrErr<Game> {
+        let opponent_move = opponent_move_for_entry(rules, entry)?;
+        let outcome = match self.outcomes.get(&entry.response_symbol) {
+            Some(x) => *x,
+            None => return Err(format!("Line {}: '{}' is not an outcome", entry.line_number, entry.response_symbol))
+        };
+        let game_plan = GamePlan{opponent_move, outcome};
+        let my_move = match rules.response_for(&game_plan) {
+            Ok(x) => x,
+            Err(e) => return Err(format!("Line {}: {}", entry.line_number, e))
+        };
+
+        return Ok(Game{my_move, opponent_move});
     }
 
-    // We lost!
-    return move_score;
+    fn describe(&self, _rules: &RuleTable) -> String {
+        let mut parts: Vec<String> = self.outcomes.iter()
+            .map(|(symbol, outcome)| format!("{}={:?}", symbol, outcome))
+            .collect();
+        parts.sort();
+        return format!("outcomes {}", parts.join(" "));
+    }
 }
 
-fn solve_a() -> i32 {
-    let games = load_input_as_games();
+fn opponent_move_for_entry(rules: &RuleTable, entry: &GuideEntry) -> ResultOrErr<Move> {
+    return match rules.opponent_move_for_symbol(entry.opponent_symbol) {
+        Some(x) => Ok(x),
+        None => Err(format!("Line {}: '{}' is not an opponent move", entry.line_number, entry.opponent_symbol))
+    };
+}
+
+fn interpret_guide(rules: &RuleTable, guide: &[GuideEntry], interpretation: &dyn Interpretation) -> ResultOrErr<Vec<Game>> {
+    return guide.iter().map(|entry| interpretation.to_game(rules, entry)).collect();
+}
 
+fn total_score(rules: &RuleTable, games: Vec<Game>) -> i32 {
     let mut my_score = 0;
 
     for game in games {
-       my_score = my_score + score_game(game);
+       my_score += score_game(rules, game);
     }
 
     return my_score;
 }
 
-fn solve_b() -> i32 {
-    let game_plans = load_input_as_game_plans();
+fn solve_a(rules: &RuleTable) -> ResultOrErr<i32> {
+    let guide = parse_guide(&load_input())?;
+    let games = interpret_guide(rules, &guide, &MoveInterpretation::from_rules(rules))?;
 
-    let mut my_score = 0;
+    return Ok(total_score(rules, games));
+}
+
+fn solve_b(rules: &RuleTable) -> ResultOrErr<i32> {
+    let guide = parse_guide(&load_input())?;
+    let games = interpret_guide(rules, &guide, &OutcomeInterpretation::from_rules(rules))?;
+
+    return Ok(total_score(rules, games));
+}
+
+#[derive(Default)]
+struct Tally {
+    score: i32,
+    wins: i32,
+    draws: i32,
+    losses: i32
+}
+
+impl Tally {
+    fn record(&mut self, rules: &RuleTable, game: Game) {
+        match rules.outcome(&game) {
+            Outcome::Win => self.wins += 1,
+            Outcome::Draw => self.draws += 1,
+            Outcome::Lose => self.losses += 1
+        }
+        self.score += score_game(rules, game);
+    }
+}
+
+struct DecodedGuide {
+    description: String,
+    is_puzzle_reading: bool,
+    tally: Tally
+}
+
+fn decode_guide(
+    rules: &RuleTable,
+    guide: &[GuideEntry],
+    interpretation: &dyn Interpretation,
+    is_puzzle_reading: bool
+) -> ResultOrErr<DecodedGuide> {
+    let mut tally = Tally::default();
+    for game in interpret_guide(rules, guide, interpretation)? {
+        tally.record(rules, game);
+    }
+
+    return Ok(DecodedGuide{description: interpretation.describe(rules), is_puzzle_reading, tally});
+}
+
+// Every way of assigning `symbol_count` symbols to distinct choices out of `choice_count`
+fn injections(symbol_count: usize, choice_count: usize) -> Vec<Vec<usize>> {
+    if symbol_count == 0 {
+        return vec![Vec::new()];
+    }
+
+    let mut output: Vec<Vec<usize>> = Vec::new();
+    for partial in injections(symbol_count - 1, choice_count) {
+        for choice in 0..choice_count {
+            if !partial.contains(&choice) {
+                let mut mapping = partial.clone();
+                mapping.push(choice);
+                output.push(mapping);
+            }
+        }
+    }
+
+    return output;
+}
+
+// Scores the guide under every reading of its second column, either as our move or as the outcome
+// we're after, to show how much the total depends on guessing the elf's meaning right
+fn decode_search(rules: &RuleTable) -> ResultOrErr<String> {
+    let guide = parse_guide(&load_input())?;
+    let mut symbols: Vec<char> = guide.iter().map(|entry| entry.response_symbol).collect();
+    symbols.sort();
+    symbols.dedup();
+
+    let outcomes = [Outcome::Lose, Outcome::Draw, Outcome::Win];
+    if symbols.len() > rules.moves.len() || symbols.len() > outcomes.len() {
+        return Err(format!("The guide uses {} response symbols, too many to map onto distinct moves and outcomes", symbols.len()));
+    }
+
+    let mut readings: Vec<DecodedGuide> = Vec::new();
+    for mapping in injections(symbols.len(), rules.moves.len()) {
+        let response_moves: HashMap<char, Move> = symbols.iter().zip(&mapping)
+            .map(|(symbol, index)| (*symbol, Move(*index)))
+            .collect();
+        let is_puzzle_reading = symbols.iter().all(|symbol| rules.moves[response_moves[symbol].0].response_symbol == *symbol);
+        readings.push(decode_guide(rules, &guide, &MoveInterpretation{response_moves}, is_puzzle_reading)?);
+    }
+
+    for mapping in injections(symbols.len(), outcomes.len()) {
+        let outcome_symbols: HashMap<char, Outcome> = symbols.iter().zip(&mapping)
+            .map(|(symbol, index)| (*symbol, outcomes[*index]))
+            .collect();
+        let is_puzzle_reading = symbols.iter().all(|symbol| rules.outcome_symbols.get(symbol) == outcome_symbols.get(symbol));
+        readings.push(decode_guide(rules, &guide, &OutcomeInterpretation{outcomes: outcome_symbols}, is_puzzle_reading)?);
+    }
+
+    readings.sort_by_key(|reading| -reading.tally.score);
+    let best_score = readings.first().map(|reading| reading.tally.score);
+    let worst_score = readings.last().map(|reading| reading.tally.score);
+
+    let mut output = String::new();
+    for reading in &readings {
+        let mut notes: Vec<&str> = Vec::new();
+        if Some(reading.tally.score) == best_score {
+            notes.push("max");
+        }
+        if Some(reading.tally.score) == worst_score {
+            notes.push("min");
+        }
+        if reading.is_puzzle_reading {
+            notes.push("puzzle");
+        }
+        output.push_str(&format!(
+            "{:<40} score {:>6}  W/D/L {}/{}/{}  {}\n",
+            reading.description, reading.tally.score, reading.tally.wins, reading.tally.draws, reading.tally.losses, notes.join(" ")
+        ));
+    }
 
-    for game_plan in game_plans {
-        if game_plan.outcome == Outcome::Draw {
-            let game = Game{opponent_move: game_plan.opponent_move, my_move: game_plan.opponent_move};
-            my_score = my_score + score_game(game);
-        } else if game_plan.outcome == Outcome::Win {
-            let my_move = match game_plan.opponent_move {
-                Move::Rock => Move::Paper,
-                Move::Paper => Move::Scissors,
-                Move::Scissors => Move::Rock
+    return Ok(output);
+}
+
+// Seeded generator behind the random bots and the --simulate rounds
+struct XorShift {
+    state: u64
+}
+
+impl XorShift {
+    fn new(seed: u64) -> XorShift {
+        // Zero is a fixed point of xorshift, so nudge the seed away from it
+        return XorShift{state: seed ^ 0x9E37_79B9_7F4A_7C15};
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        return self.state;
+    }
+
+    fn below(&mut self, limit: usize) -> usize {
+        return (self.next() % limit as u64) as usize;
+    }
+}
+
+// A player in the tournament. Bots see each round's result after it is played.
+trait Bot {
+    fn name(&self) -> String;
+    fn choose(&mut self, rules: &RuleTable) -> Move;
+    fn observe(&mut self, my_move: Move, opponent_move: Move, outcome: Outcome);
+}
+
+// Plays the moves from the strategy guide's second column in order, starting over when it runs out
+struct GuideBot {
+    moves: Vec<Move>,
+    next_index: usize
+}
+
+// Plays whatever beats the opponent's most common move so far
+struct FrequencyBot {
+    counts: Vec<usize>
+}
+
+// Predicts the opponent's next move from what they've played after their last move before
+struct MarkovBot {
+    transitions: Vec<Vec<usize>>,
+    last_opponent_move: Option<Move>
+}
+
+// Sticks with a winning move, otherwise switches to whatever would have beaten the opponent last round
+struct WinStayLoseShiftBot {
+    last_round: Option<(Move, Move, Outcome)>
+}
+
+struct RandomBot {
+    rng: XorShift
+}
+
+// The best-scoring move that beats `target`, or `target` itself when nothing beats it
+fn counter_move(rules: &RuleTable, target: Move) -> Move {
+    let mut best = target;
+    for candidate in rules.all_moves() {
+        if !rules.beats[candidate.0][target.0] {
+            continue
+        }
+        if best == target || rules.moves[candidate.0].shape_score > rules.moves[best.0].shape_score {
+            best = candidate;
+        }
+    }
+
+    return best;
+}
+
+// The index of the biggest count, favouring earlier moves on ties
+fn most_common(counts: &[usize]) -> Option<Move> {
+    let mut best: Option<usize> = None;
+    for (index, count) in counts.iter().enumerate() {
+        if *count > 0 && (best.is_none() || *count > counts[best.unwrap()]) {
+            best = Some(index);
+        }
+    }
+
+    return best.map(Move);
+}
+
+impl Bot for GuideBot {
+    fn name(&self) -> String {
+        return "guide".to_string();
+    }
+
+    fn choose(&mut self, _rules: &RuleTable) -> Move {
+        if self.moves.is_empty() {
+            return Move(0);
+        }
+        let my_move = self.moves[self.next_index % self.moves.len()];
+        self.next_index += 1;
+        return my_move;
+    }
+
+    fn observe(&mut self, _my_move: Move, _opponent_move: Move, _outcome: Outcome) {}
+}
+
+impl Bot for FrequencyBot {
+    fn name(&self) -> String {
+        return "frequency".to_string();
+    }
+
+    fn choose(&mut self, rules: &RuleTable) -> Move {
+        return match most_common(&self.counts) {
+            Some(predicted) => counter_move(rules, predicted),
+            None => Move(0)
+        };
+    }
+
+    fn observe(&mut self, _my_move: Move, opponent_move: Move, _outcome: Outcome) {
+        self.counts[opponent_move.0] += 1;
+    }
+}
+
+impl Bot for MarkovBot {
+    fn name(&self) -> String {
+        return "markov".to_string();
+    }
+
+    fn choose(&mut self, rules: &RuleTable) -> Move {
+        let predicted = match self.last_opponent_move {
+            Some(last) => most_common(&self.transitions[last.0]),
+            None => None
+        };
+        return match predicted {
+            Some(x) => counter_move(rules, x),
+            None => Move(0)
+        };
+    }
+
+    fn observe(&mut self, _my_move: Move, opponent_move: Move, _outcome: Outcome) {
+        if let Some(last) = self.last_opponent_move {
+            self.transitions[last.0][opponent_move.0] += 1;
+        }
+        self.last_opponent_move = Some(opponent_move);
+    }
+}
+
+impl Bot for WinStayLoseShiftBot {
+    fn name(&self) -> String {
+        return "win-stay-lose-shift".to_string();
+    }
+
+    fn choose(&mut self, rules: &RuleTable) -> Move {
+        return match self.last_round {
+            None => Move(0),
+            Some((my_move, _, Outcome::Win)) => my_move,
+            Some((_, opponent_move, _)) => counter_move(rules, opponent_move)
+        };
+    }
+
+    fn observe(&mut self, my_move: Move, opponent_move: Move, outcome: Outcome) {
+        self.last_round = Some((my_move, opponent_move, outcome));
+    }
+}
+
+impl Bot for RandomBot {
+    fn name(&self) -> String {
+        return "random".to_string();
+    }
+
+    fn choose(&mut self, rules: &RuleTable) -> Move {
+        return Move(self.rng.below(rules.moves.len()));
+    }
+
+    fn observe(&mut self, _my_move: Move, _opponent_move: Move, _outcome: Outcome) {}
+}
+
+// A fresh line-up of bots, so no bot carries memories from one match into the next
+fn make_bots(rules: &RuleTable, guide_moves: &[Move], seed: u64) -> Vec<Box<dyn Bot>> {
+    let move_count = rules.moves.len();
+    return vec![
+        Box::new(GuideBot{moves: guide_moves.to_vec(), next_index: 0}),
+        Box::new(FrequencyBot{counts: vec![0; move_count]}),
+        Box::new(MarkovBot{transitions: vec![vec![0; move_count]; move_count], last_opponent_move: None}),
+        Box::new(WinStayLoseShiftBot{last_round: None}),
+        Box::new(RandomBot{rng: XorShift::new(seed)})
+    ];
+}
+
+struct MatchResult {
+    scores: [i32; 2],
+    round_wins: [usize; 2]
+}
+
+fn play_match(rules: &RuleTable, bots: [&mut Box<dyn Bot>; 2], rounds: usize) -> MatchResult {
+    let [first, second] = bots;
+    let mut result = MatchResult{scores: [0, 0], round_wins: [0, 0]};
+    for _round in 0..rounds {
+        let first_move = first.choose(rules);
+        let second_move = second.choose(rules);
+
+        let first_game = Game{my_move: first_move, opponent_move: second_move};
+        let second_game = Game{my_move: second_move, opponent_move: first_move};
+        let first_outcome = rules.outcome(&first_game);
+        let second_outcome = rules.outcome(&second_game);
+        match first_outcome {
+            Outcome::Win => result.round_wins[0] += 1,
+            Outcome::Lose => result.round_wins[1] += 1,
+            Outcome::Draw => {}
+        }
+        result.scores[0] += score_game(rules, first_game);
+        result.scores[1] += score_game(rules, second_game);
+
+        first.observe(first_move, second_move, first_outcome);
+        second.observe(second_move, first_move, second_outcome);
+    }
+
+    return result;
+}
+
+#[derive(Default)]
+struct Standing {
+    match_wins: usize,
+    match_draws: usize,
+    match_losses: usize,
+    total_score: i64
+}
+
+// Every bot plays every other bot once over `rounds` rounds. A match is won by the higher total score.
+fn run_tournament(rules: &RuleTable, rounds: usize, seed: u64) -> ResultOrErr<String> {
+    let guide = parse_guide(&load_input())?;
+    let guide_moves: Vec<Move> = interpret_guide(rules, &guide, &MoveInterpretation::from_rules(rules))?
+        .iter()
+        .map(|game| game.my_move)
+        .collect();
+
+    let names: Vec<String> = make_bots(rules, &guide_moves, seed).iter().map(|bot| bot.name()).collect();
+    let bot_count = names.len();
+    let mut standings: Vec<Standing> = (0..bot_count).map(|_| Standing::default()).collect();
+    let mut score_matrix: Vec<Vec<Option<i32>>> = vec![vec![None; bot_count]; bot_count];
+
+    let mut match_index = 0;
+    for i in 0..bot_count {
+        for j in (i + 1)..bot_count {
+            // Separate line-ups so each side gets its own freshly seeded bot
+            let mut first_line_up = make_bots(rules, &guide_moves, seed.wrapping_add(2 * match_index));
+            let mut second_line_up = make_bots(rules, &guide_moves, seed.wrapping_add(2 * match_index + 1));
+            let result = play_match(rules, [&mut first_line_up[i], &mut second_line_up[j]], rounds);
+            match_index += 1;
+
+            score_matrix[i][j] = Some(result.scores[0]);
+            score_matrix[j][i] = Some(result.scores[1]);
+            standings[i].total_score += result.scores[0] as i64;
+            standings[j].total_score += result.scores[1] as i64;
+            if result.scores[0] > result.scores[1] {
+                standings[i].match_wins += 1;
+                standings[j].match_losses += 1;
+            } else if result.scores[0] < result.scores[1] {
+                standings[j].match_wins += 1;
+                standings[i].match_losses += 1;
+            } else {
+                standings[i].match_draws += 1;
+                standings[j].match_draws += 1;
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..bot_count).collect();
+    order.sort_by_key(|index| {
+        let standing = &standings[*index];
+        (-(3 * standing.match_wins as i64 + standing.match_draws as i64), -standing.total_score)
+    });
+
+    let name_width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+    let mut output = format!("Standings after {} rounds per match (3 points per match win, 1 per draw)\n", rounds);
+    output.push_str(&format!("{:<width$}  {:>3} {:>3} {:>3} {:>6} {:>10}\n", "bot", "W", "D", "L", "points", "score", width = name_width));
+    for index in &order {
+        let standing = &standings[*index];
+        output.push_str(&format!(
+            "{:<width$}  {:>3} {:>3} {:>3} {:>6} {:>10}\n",
+            names[*index], standing.match_wins, standing.match_draws, standing.match_losses,
+            3 * standing.match_wins + standing.match_draws, standing.total_score, width = name_width
+        ));
+    }
+
+    output.push_str("\nScore of each row bot against each column bot\n");
+    output.push_str(&format!("{:<width$}", "", width = name_width));
+    for name in &names {
+        output.push_str(&format!("  {:>width$}", name, width = name.len().max(8)));
+    }
+    output.push('\n');
+    for (i, row) in score_matrix.iter().enumerate() {
+        output.push_str(&format!("{:<width$}", names[i], width = name_width));
+        for (j, score) in row.iter().enumerate() {
+            let cell = match score {
+                Some(x) => x.to_string(),
+                None => "-".to_string()
             };
-            let game = Game{opponent_move: game_plan.opponent_move, my_move};
-            my_score = my_score + score_game(game);
+            output.push_str(&format!("  {:>width$}", cell, width = names[j].len().max(8)));
+        }
+        output.push('\n');
+    }
+
+    return Ok(output);
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    return if b == 0 { a.abs() } else { gcd(b, a % b) };
+}
+
+// Exact fraction, always stored in lowest terms with a positive denominator
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Rational {
+    numerator: i128,
+    denominator: i128
+}
+
+impl Rational {
+    fn new(numerator: i128, denominator: i128) -> Rational {
+        assert!(denominator != 0, "Rational with zero denominator");
+        let sign = if denominator < 0 { -1 } else { 1 };
+        let divisor = gcd(numerator, denominator).max(1);
+        return Rational{numerator: sign * numerator / divisor, denominator: sign * denominator / divisor};
+    }
+
+    fn from_int(value: i128) -> Rational {
+        return Rational{numerator: value, denominator: 1};
+    }
+
+    fn zero() -> Rational {
+        return Rational::from_int(0);
+    }
+
+    fn to_f64(self) -> f64 {
+        return self.numerator as f64 / self.denominator as f64;
+    }
+
+    // Accepts whole numbers and fractions like "3/4"
+    fn parse(input: &str) -> ResultOrErr<Rational> {
+        let parts: Vec<&str> = input.split('/').collect();
+        let parse_part = |part: &str| match part.trim().parse::<i128>() {
+            Ok(x) => Ok(x),
+            Err(_) => Err(format!("Could not parse '{}' as a number", input))
+        };
+        return match parts.len() {
+            1 => Ok(Rational::from_int(parse_part(parts[0])?)),
+            2 => {
+                let denominator = parse_part(parts[1])?;
+                if denominator == 0 {
+                    return Err(format!("'{}' has a zero denominator", input));
+                }
+                Ok(Rational::new(parse_part(parts[0])?, denominator))
+            },
+            _ => Err(format!("Could not parse '{}' as a number", input))
+        };
+    }
+}
+
+impl Add for Rational {
+    type Output = Rational;
+    fn add(self, other: Rational) -> Rational {
+        return Rational::new(self.numerator * other.denominator + other.numerator * self.denominator, self.denominator * other.denominator);
+    }
+}
+
+impl Sub for Rational {
+    type Output = Rational;
+    fn sub(self, other: Rational) -> Rational {
+        return Rational::new(self.numerator * other.denominator - other.numerator * self.denominator, self.denominator * other.denominator);
+    }
+}
+
+impl Mul for Rational {
+    type Output = Rational;
+    fn mul(self, other: Rational) -> Rational {
+        return Rational::new(self.numerator * other.numerator, self.denominator * other.denominator);
+    }
+}
+
+impl Div for Rational {
+    type Output = Rational;
+    fn div(self, other: Rational) -> Rational {
+        return Rational::new(self.numerator * other.denominator, self.denominator * other.numerator);
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Rational) -> Ordering {
+        // Denominators are positive, so cross-multiplying keeps the order
+        return (self.numerator * other.denominator).cmp(&(other.numerator * self.denominator));
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return if self.denominator == 1 {
+            write!(f, "{}", self.numerator)
         } else {
-            let my_move = match game_plan.opponent_move {
-                Move::Rock => Move::Scissors,
-                Move::Paper => Move::Rock,
-                Move::Scissors => Move::Paper
-            };
-            let game = Game{opponent_move: game_plan.opponent_move, my_move};
-            my_score = my_score + score_game(game);
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        };
+    }
+}
+
+// How we respond to each opponent move: responses[opponent move][my move] is the chance of playing my move
+struct Policy {
+    name: String,
+    responses: Vec<Vec<Rational>>
+}
+
+impl Policy {
+    fn always(rules: &RuleTable, my_move: Move) -> Policy {
+        let mut row = vec![Rational::zero(); rules.moves.len()];
+        row[my_move.0] = Rational::from_int(1);
+        return Policy{
+            name: format!("always {}", rules.moves[my_move.0].name),
+            responses: vec![row; rules.moves.len()]
+        };
+    }
+
+    // The mix of moves the guide plays against each opponent move. Opponent moves the guide never
+    // mentions get a uniform response, as the guide says nothing about them.
+    fn from_games(rules: &RuleTable, name: &str, games: &[Game]) -> Policy {
+        let move_count = rules.moves.len();
+        let mut counts: Vec<Vec<i128>> = vec![vec![0; move_count]; move_count];
+        for game in games {
+            counts[game.opponent_move.0][game.my_move.0] += 1;
         }
+
+        let responses = counts.iter().map(|row| {
+            let total: i128 = row.iter().sum();
+            if total == 0 {
+                vec![Rational::new(1, move_count as i128); move_count]
+            } else {
+                row.iter().map(|count| Rational::new(*count, total)).collect()
+            }
+        }).collect();
+
+        return Policy{name: name.to_string(), responses};
     }
 
-    return my_score;
+    // Mean and variance of the score for one round against an opponent playing `distribution`
+    fn score_moments(&self, rules: &RuleTable, distribution: &[Rational]) -> (Rational, Rational) {
+        let mut mean = Rational::zero();
+        let mut mean_square = Rational::zero();
+        for opponent_move in rules.all_moves() {
+            for my_move in rules.all_moves() {
+                let probability = distribution[opponent_move.0] * self.responses[opponent_move.0][my_move.0];
+                let score = Rational::from_int(score_game(rules, Game{my_move, opponent_move}) as i128);
+                mean = mean + probability * score;
+                mean_square = mean_square + probability * score * score;
+            }
+        }
+
+        return (mean, mean_square - mean * mean);
+    }
+}
+
+// Either weights like "1:2:1" (one per move, in rule table order) or "guide" to count the opponent's
+// moves in the guide's first column
+fn opponent_distribution(rules: &RuleTable, guide: &[GuideEntry], spec: &str) -> ResultOrErr<Vec<Rational>> {
+    let weights: Vec<Rational> = if spec == "guide" {
+        let mut counts = vec![Rational::zero(); rules.moves.len()];
+        for entry in guide {
+            let opponent_move = opponent_move_for_entry(rules, entry)?;
+            counts[opponent_move.0] = counts[opponent_move.0] + Rational::from_int(1);
+        }
+        counts
+    } else {
+        let weights = spec.split([':', ',']).map(Rational::parse).collect::<ResultOrErr<Vec<Rational>>>()?;
+        if weights.len() != rules.moves.len() {
+            return Err(format!("Expected {} weights, one per move, but got {}", rules.moves.len(), weights.len()));
+        }
+        weights
+    };
+
+    if weights.iter().any(|weight| *weight < Rational::zero()) {
+        return Err("Weights can't be negative".to_string());
+    }
+    let total = weights.iter().fold(Rational::zero(), |sum, weight| sum + *weight);
+    if total == Rational::zero() {
+        return Err("Weights must not all be zero".to_string());
+    }
+
+    return Ok(weights.iter().map(|weight| *weight / total).collect());
+}
+
+// Plays `rounds` rounds against opponent moves drawn from `distribution` and returns the mean score
+fn simulate_policy(rules: &RuleTable, policy: &Policy, distribution: &[Rational], rounds: usize, rng: &mut XorShift) -> f64 {
+    let sample = |weights: &[Rational], rng: &mut XorShift| {
+        let target = (rng.next() >> 11) as f64 / (1u64 << 53) as f64;
+        let mut cumulative = 0.0;
+        for (index, weight) in weights.iter().enumerate() {
+            cumulative += weight.to_f64();
+            if target < cumulative {
+                return Move(index);
+            }
+        }
+        // Rounding can leave the cumulative total a hair under 1
+        return Move(weights.iter().rposition(|weight| *weight > Rational::zero()).unwrap());
+    };
+
+    let mut total: i64 = 0;
+    for _round in 0..rounds {
+        let opponent_move = sample(distribution, rng);
+        let my_move = sample(&policy.responses[opponent_move.0], rng);
+        total += score_game(rules, Game{my_move, opponent_move}) as i64;
+    }
+
+    return total as f64 / rounds as f64;
+}
+
+fn expected_scores(rules: &RuleTable, distribution_spec: &str, simulated_rounds: usize, seed: u64) -> ResultOrErr<String> {
+    let guide = parse_guide(&load_input())?;
+    let distribution = opponent_distribution(rules, &guide, distribution_spec)?;
+
+    let mut policies: Vec<Policy> = rules.all_moves().map(|my_move| Policy::always(rules, my_move)).collect();
+    let guide_moves = interpret_guide(rules, &guide, &MoveInterpretation::from_rules(rules))?;
+    policies.push(Policy::from_games(rules, "guide moves (part A)", &guide_moves));
+    let guide_outcomes = interpret_guide(rules, &guide, &OutcomeInterpretation::from_rules(rules))?;
+    policies.push(Policy::from_games(rules, "guide outcomes (part B)", &guide_outcomes));
+
+    // Without knowing the opponent's move, the expected score is linear in our mix of moves, so the best
+    // mixes are exactly those made only of the moves with the best expected score. We report the even mix.
+    let move_values: Vec<Rational> = rules.all_moves()
+        .map(|my_move| Policy::always(rules, my_move).score_moments(rules, &distribution).0)
+        .collect();
+    let best_value = *move_values.iter().max().unwrap();
+    let best_moves: Vec<Move> = rules.all_moves().filter(|my_move| move_values[my_move.0] == best_value).collect();
+    let mut best_mix = vec![Rational::zero(); rules.moves.len()];
+    for my_move in &best_moves {
+        best_mix[my_move.0] = Rational::new(1, best_moves.len() as i128);
+    }
+    let best_mix_name: Vec<String> = best_moves.iter()
+        .map(|my_move| format!("{} {}", rules.moves[my_move.0].name, best_mix[my_move.0]))
+        .collect();
+    policies.push(Policy{
+        name: format!("best mix ({})", best_mix_name.join(", ")),
+        responses: vec![best_mix; rules.moves.len()]
+    });
+
+    let distribution_names: Vec<String> = rules.all_moves()
+        .map(|opponent_move| format!("{} {}", rules.moves[opponent_move.0].name, distribution[opponent_move.0]))
+        .collect();
+    let mut output = format!("Opponent plays: {}\n", distribution_names.join(", "));
+
+    let name_width = policies.iter().map(|policy| policy.name.len()).max().unwrap_or(0);
+    output.push_str(&format!("{:<width$}  {:>24}  {:>24}", "policy", "expected score", "variance", width = name_width));
+    if simulated_rounds > 0 {
+        output.push_str(&format!("  {:>22}", format!("simulated ({} rounds)", simulated_rounds)));
+    }
+    output.push('\n');
+
+    let mut rng = XorShift::new(seed);
+    for policy in &policies {
+        let (mean, variance) = policy.score_moments(rules, &distribution);
+        output.push_str(&format!(
+            "{:<width$}  {:>24}  {:>24}",
+            policy.name,
+            format!("{} ({:.4})", mean, mean.to_f64()),
+            format!("{} ({:.4})", variance, variance.to_f64()),
+            width = name_width
+        ));
+        if simulated_rounds > 0 {
+            let simulated = simulate_policy(rules, policy, &distribution, simulated_rounds, &mut rng);
+            output.push_str(&format!("  {:>12.4} ({:+.4})", simulated, simulated - mean.to_f64()));
+        }
+        output.push('\n');
+    }
+
+    return Ok(output);
+}
+
+fn get_option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let position = args.iter().position(|arg| arg == name)?;
+    return args.get(position + 1).map(|value| &value[..]);
 }
 
 fn is_example_mode() -> bool {
@@ -118,64 +1220,148 @@ fn is_example_mode() -> bool {
     return example_mode.unwrap() == "1"
 }
 
-fn load_input_as_games() -> Vec<Game> {
+fn load_input() -> String {
     let filename = if is_example_mode() { "exampleInput.txt" } else { "input.txt" };
     let mut path = String::from("../");
     path.push_str(filename);
 
-    let contents : String = fs::read_to_string(path)
+    return fs::read_to_string(path)
         .expect("Something went wrong reading the file");
+}
 
-    let mut output: Vec<Game> = Vec::new();
-    for line in contents.lines() {
-        if line == "" {
+fn parse_guide(contents: &str) -> ResultOrErr<Vec<GuideEntry>> {
+    let mut output: Vec<GuideEntry> = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        if line.is_empty() {
             continue
         }
-        let opponent_move: Move = match line.chars().nth(0).unwrap() {
-            'A' => Move::Rock,
-            'B' => Move::Paper,
-            'C' => Move::Scissors,
-            _ => panic!("Could not parse opponent_move")
-        };
-        let my_move: Move = match line.chars().nth(2).unwrap() {
-            'X' => Move::Rock,
-            'Y' => Move::Paper,
-            'Z' => Move::Scissors,
-            _ => panic!("Could not parse my_move")
-        };
-        output.push(Game{my_move, opponent_move});
+        let chars: Vec<char> = line.chars().collect();
+        if chars.len() != 3 || chars[1] != ' ' {
+            return Err(format!("Line {}: expected '<opponent symbol> <response symbol>', got '{}'", index + 1, line));
+        }
+        output.push(GuideEntry{line_number: index + 1, opponent_symbol: chars[0], response_symbol: chars[2]});
     }
 
-    return output;
+    return Ok(output);
 }
 
-fn load_input_as_game_plans() -> Vec<GamePlan> {
-    let filename = if is_example_mode() { "exampleInput.txt" } else { "input.txt" };
-    let mut path = String::from("../");
-    path.push_str(filename);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let contents : String = fs::read_to_string(path)
-        .expect("Something went wrong reading the file");
+    const EXAMPLE: &str = include_str!("../../exampleInput.txt");
 
-    let mut output: Vec<GamePlan> = Vec::new();
-    for line in contents.lines() {
-        if line == "" {
-            continue
+    fn scores(rules: &RuleTable) -> (i32, i32) {
+        let guide = parse_guide(EXAMPLE).unwrap();
+        let moves = interpret_guide(rules, &guide, &MoveInterpretation::from_rules(rules)).unwrap();
+        let outcomes = interpret_guide(rules, &guide, &OutcomeInterpretation::from_rules(rules)).unwrap();
+        return (total_score(rules, moves), total_score(rules, outcomes));
+    }
+
+    #[test]
+    fn classic_rules_solve_the_example() {
+        assert_eq!(scores(&load_rules(Some("classic")).unwrap()), (15, 12));
+        assert_eq!(scores(&load_rules(None).unwrap()), (15, 12));
+    }
+
+    #[test]
+    fn three_move_cyclic_game_is_classic() {
+        let classic = load_rules(Some("classic")).unwrap();
+        let cyclic = load_rules(Some("cyclic:3")).unwrap();
+        assert_eq!(cyclic.beats, classic.beats);
+        assert_eq!(scores(&cyclic), (15, 12));
+    }
+
+    #[test]
+    fn every_rpsls_move_beats_two_others() {
+        let rules = load_rules(Some("rpsls")).unwrap();
+        for row in &rules.beats {
+            assert_eq!(row.iter().filter(|beats| **beats).count(), 2);
         }
-        let opponent_move: Move = match line.chars().nth(0).unwrap() {
-            'A' => Move::Rock,
-            'B' => Move::Paper,
-            'C' => Move::Scissors,
-            _ => panic!("Could not parse opponent_move")
-        };
-        let outcome: Outcome = match line.chars().nth(2).unwrap() {
-            'X' => Outcome::Lose,
-            'Y' => Outcome::Draw,
-            'Z' => Outcome::Win,
-            _ => panic!("Could not parse outcome")
-        };
-        output.push(GamePlan{opponent_move, outcome});
     }
 
-    return output;
+    #[test]
+    fn cyclic_needs_an_odd_move_count() {
+        assert!(RuleTable::cyclic(4).is_err());
+        assert!(RuleTable::cyclic(15).is_err());
+        for move_count in [5, 7, 13] {
+            let rules = RuleTable::cyclic(move_count).unwrap();
+            for row in &rules.beats {
+                assert_eq!(row.iter().filter(|beats| **beats).count(), move_count / 2);
+            }
+        }
+    }
+
+    #[test]
+    fn rationals_are_kept_in_lowest_terms() {
+        assert_eq!(Rational::new(2, 4), Rational{numerator: 1, denominator: 2});
+        assert_eq!(Rational::new(3, -6), Rational{numerator: -1, denominator: 2});
+        assert_eq!(Rational::new(-3, -6), Rational{numerator: 1, denominator: 2});
+        assert_eq!(Rational::new(0, -5), Rational::zero());
+        assert_eq!(Rational::new(1, 6) + Rational::new(1, 3), Rational::new(1, 2));
+        assert_eq!(Rational::new(1, 3) - Rational::new(1, 2), Rational::new(-1, 6));
+        assert_eq!(Rational::new(2, 3) * Rational::new(3, 4), Rational::new(1, 2));
+        assert_eq!(Rational::new(1, 2) / Rational::new(-1, 4), Rational::from_int(-2));
+        assert_eq!(Rational::new(-4, 6).to_string(), "-2/3");
+        assert_eq!(Rational::new(6, 3).to_string(), "2");
+    }
+
+    #[test]
+    fn rationals_order_with_negatives() {
+        assert!(Rational::new(-1, 2) < Rational::new(-1, 3));
+        assert!(Rational::new(-1, 2) < Rational::zero());
+        assert!(Rational::new(1, -3) > Rational::new(2, -3));
+        let mut values = vec![Rational::new(1, 2), Rational::new(-3, 4), Rational::zero(), Rational::new(-1, 4)];
+        values.sort();
+        assert_eq!(values, vec![Rational::new(-3, 4), Rational::new(-1, 4), Rational::zero(), Rational::new(1, 2)]);
+    }
+
+    #[test]
+    fn rational_parse() {
+        assert_eq!(Rational::parse("3/6"), Ok(Rational::new(1, 2)));
+        assert_eq!(Rational::parse(" 4 "), Ok(Rational::from_int(4)));
+        assert_eq!(Rational::parse("1/-2"), Ok(Rational::new(-1, 2)));
+        assert_eq!(Rational::parse("1/0"), Err("'1/0' has a zero denominator".to_string()));
+        assert_eq!(Rational::parse("1/2/3"), Err("Could not parse '1/2/3' as a number".to_string()));
+        assert_eq!(Rational::parse("x"), Err("Could not parse 'x' as a number".to_string()));
+    }
+
+    #[test]
+    fn always_scissors_against_a_uniform_opponent() {
+        // Scissors scores 3 + 0 against Rock, 3 + 6 against Paper and 3 + 3 against Scissors: 3, 9 and 6 with
+        // mean 6 and variance (9 + 9 + 0) / 3 = 6
+        let rules = load_rules(Some("classic")).unwrap();
+        let distribution = opponent_distribution(&rules, &[], "1:1:1").unwrap();
+        let scissors = rules.response_symbols()[&'Z'];
+        assert_eq!(Policy::always(&rules, scissors).score_moments(&rules, &distribution), (Rational::from_int(6), Rational::from_int(6)));
+    }
+
+    #[test]
+    fn parse_errors_cite_their_line() {
+        let rules = "move Rock 1 A X\n\n# comment\nmove Paper 2 B Y\n";
+        assert_eq!(
+            RuleTable::parse(&format!("{}beats Rock Scissors\n", rules)).err(),
+            Some("Line 5: unknown move 'Scissors'".to_string())
+        );
+        assert_eq!(
+            RuleTable::parse(&format!("{}move Scissors 3 C X\n", rules)).err(),
+            Some("Line 5: moves 'Rock' and 'Scissors' share response symbol 'X'".to_string())
+        );
+        assert_eq!(
+            RuleTable::parse(&format!("{}move Scissors 3 B Z\n", rules)).err(),
+            Some("Line 5: moves 'Paper' and 'Scissors' share opponent symbol 'B'".to_string())
+        );
+        assert_eq!(
+            RuleTable::parse(&format!("{}move Rock 3 C Z\n", rules)).err(),
+            Some("Line 5: move 'Rock' is declared twice".to_string())
+        );
+        assert_eq!(
+            RuleTable::parse(&format!("{}beats Paper Rock\nbeats Rock Paper\n", rules)).err(),
+            Some("Line 6: moves 'Rock' and 'Paper' can't both beat each other".to_string())
+        );
+        assert_eq!(
+            RuleTable::parse(&format!("{}beats Rock Rock\n", rules)).err(),
+            Some("Line 5: move 'Rock' can't beat itself".to_string())
+        );
+    }
 }
@@ -1,17 +1,26 @@
+#![allow(clippy::needless_return)]
+
 use std::env;
 use std::fs;
 use core::str::Lines;
 use std::collections::HashMap;
+use std::time::Instant;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     let input_filename = &(&args[2])[..];
+    let options = match parse_options(&args[3..]) {
+        Ok(x) => x,
+        Err(e) => panic!("{}", e)
+    };
 
-    let result = match &(&args[1])[..] {
-        "--solve-a" => solve_a(input_filename),
-        "--solve-b" => solve_b(input_filename),
-        _ => panic!("Must provide --solve-a or --solve-b as first arg")
+    let result = match &options.alphabet {
+        Some(alphabet_filename) => match AlphabetScheme::load(alphabet_filename) {
+            Ok(scheme) => run(&args[1], input_filename, &options, &scheme),
+            Err(e) => Err(e)
+        },
+        None => run(&args[1], input_filename, &options, &DefaultScheme)
     };
 
     match result {
@@ -20,54 +29,754 @@ fn main() {
     }
 }
 
+fn run<S: PriorityScheme>(mode: &str, input_filename: &str, options: &Options, scheme: &S) -> ResultOrErr<String> {
+    return match mode {
+        "--solve-a" => solve_a(input_filename, options, scheme).map(|x| x.to_string()),
+        "--solve-b" => solve_b(input_filename, options, scheme).map(|x| x.to_string()),
+        "--report" => report(input_filename, options, scheme),
+        "--infer-groups" => infer_groups(input_filename, options, scheme),
+        "--repack" => repack(input_filename, options, scheme),
+        // Takes a line count rather than a file name, and always uses the default priorities
+        "--bench" => bench(input_filename),
+        _ => panic!("Must provide --solve-a, --solve-b, --report, --infer-groups, --repack or --bench as first arg")
+    };
+}
+
 type ResultOrErr<X> = Result<X, String>;
 #[derive(Clone,Copy)]
 struct Rucksack<'a> {
+    line_number: usize,
     pouch_size: usize,
-    contents: &'a str
+    contents: &'a str,
+    first_compartment: ItemSet,
+    second_compartment: ItemSet
 }
 type ItemType = char;
-type ElfGroup<'a> = [Rucksack<'a>; 3];
+type ElfGroup<'a> = [Rucksack<'a>];
+
+struct Options {
+    group_size: usize,
+    // Fail unless every rucksack and group has exactly one shared item type
+    strict: bool,
+    // Let the repacking planner swap items between rucksacks in the same group when a rucksack can't be fixed on
+    // its own. This is a greedy repair, so the total number of swaps isn't guaranteed to be the fewest.
+    group_repair: bool,
+    // File listing the item types in priority order, instead of a-z then A-Z
+    alphabet: Option<String>
+}
+
+fn parse_options(args: &[String]) -> ResultOrErr<Options> {
+    let mut options = Options{group_size: 3, strict: false, group_repair: false, alphabet: None};
+    let mut index = 0;
+    while index < args.len() {
+        match &args[index][..] {
+            "--strict" => options.strict = true,
+            "--group-repair" => options.group_repair = true,
+            "--alphabet" => {
+                index += 1;
+                options.alphabet = match args.get(index) {
+                    Some(x) => Some(x.clone()),
+                    None => return Err("--alphabet must be followed by a file name".to_string())
+                };
+            },
+            "--group-size" => {
+                index += 1;
+                options.group_size = match args.get(index).map(|x| x.parse::<usize>()) {
+                    Some(Ok(x)) if x > 0 => x,
+                    _ => return Err("--group-size must be followed by a positive number".to_string())
+                };
+            },
+            other => return Err(format!("Unknown option '{}'", other))
+        }
+        index += 1;
+    }
+
+    return Ok(options);
+}
+
+// Highest priority an item type can have and still fit in an ItemSet
+const MAX_PRIORITY: u32 = 63;
+
+// Maps item types to priorities from 1 to MAX_PRIORITY and back
+trait PriorityScheme {
+    // None for characters that aren't item types under this scheme
+    fn priority(&self, item_type: ItemType) -> Option<u32>;
+    fn item_type(&self, priority: u32) -> Option<ItemType>;
+}
+
+// The puzzle's priorities: a-z are 1 to 26 and A-Z are 27 to 52
+struct DefaultScheme;
+
+// Item types listed in priority order in a file, so the first character has priority 1
+struct AlphabetScheme {
+    alphabet: Vec<ItemType>,
+    priorities: HashMap<ItemType, u32>
+}
+
+impl PriorityScheme for DefaultScheme {
+    fn priority(&self, item_type: ItemType) -> Option<u32> {
+        if !item_type.is_ascii() {
+            return None;
+        }
+        return match PRIORITY_TABLE[item_type as usize] {
+            0 => None,
+            priority => Some(priority as u32)
+        };
+    }
+
+    fn item_type(&self, priority: u32) -> Option<ItemType> {
+        return match priority {
+            1..=52 => Some(ITEM_TYPE_TABLE[priority as usize]),
+            _ => None
+        };
+    }
+}
+
+impl AlphabetScheme {
+    fn load(filename: &str) -> ResultOrErr<AlphabetScheme> {
+        return AlphabetScheme::parse(&load_input(filename)?);
+    }
+
+    // Whitespace is ignored, so long alphabets can be split over several lines
+    fn parse(input: &str) -> ResultOrErr<AlphabetScheme> {
+        let mut scheme = AlphabetScheme{alphabet: Vec::new(), priorities: HashMap::new()};
+        for (index, line) in input.lines().enumerate() {
+            for (column, item_type) in line.chars().enumerate() {
+                if item_type.is_whitespace() {
+                    continue
+                }
+                if scheme.priorities.contains_key(&item_type) {
+                    return Err(format!("Alphabet line {}, column {}: '{}' is listed twice", index + 1, column + 1, item_type));
+                }
+                scheme.alphabet.push(item_type);
+                scheme.priorities.insert(item_type, scheme.alphabet.len() as u32);
+            }
+        }
+        if scheme.alphabet.is_empty() {
+            return Err("Alphabet is empty".to_string());
+        }
+        if scheme.alphabet.len() > MAX_PRIORITY as usize {
+            return Err(format!("Alphabet has {} item types but at most {} are supported", scheme.alphabet.len(), MAX_PRIORITY));
+        }
+
+        return Ok(scheme);
+    }
+}
+
+impl PriorityScheme for AlphabetScheme {
+    fn priority(&self, item_type: ItemType) -> Option<u32> {
+        return self.priorities.get(&item_type).copied();
+    }
+
+    fn item_type(&self, priority: u32) -> Option<ItemType> {
+        return match priority {
+            0 => None,
+            _ => self.alphabet.get(priority as usize - 1).copied()
+        };
+    }
+}
+
+// Set of item types, with bit n set when the item type with priority n is present.
+// Priorities run from 1 to MAX_PRIORITY, so they all fit in a u64.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+struct ItemSet(u64);
+
+impl ItemSet {
+    fn insert(&mut self, priority: u32) {
+        self.0 |= 1 << priority;
+    }
+
+    fn intersection(self, other: ItemSet) -> ItemSet {
+        return ItemSet(self.0 & other.0);
+    }
+
+    fn union(self, other: ItemSet) -> ItemSet {
+        return ItemSet(self.0 | other.0);
+    }
+
+    fn len(self) -> u32 {
+        return self.0.count_ones();
+    }
+
+    fn item_types<S: PriorityScheme>(self, scheme: &S) -> Vec<ItemType> {
+        return (0..64).filter(|bit| self.0 & (1 << bit) != 0).filter_map(|bit| scheme.item_type(bit)).collect();
+    }
+
+    // The item type with the lowest priority in the set
+    fn first<S: PriorityScheme>(self, scheme: &S) -> Option<ItemType> {
+        if self.0 == 0 {
+            return None;
+        }
+        return scheme.item_type(self.0.trailing_zeros());
+    }
+}
+
+const fn build_priority_table() -> [u8; 128] {
+    let mut table = [0; 128];
+    let mut offset = 0;
+    while offset < 26 {
+        table[b'a' as usize + offset] = offset as u8 + 1;
+        table[b'A' as usize + offset] = offset as u8 + 27;
+        offset += 1;
+    }
+    return table;
+}
+
+const fn build_item_type_table() -> [char; 64] {
+    let mut table = ['?'; 64];
+    let mut offset = 0;
+    while offset < 26 {
+        table[offset + 1] = (b'a' + offset as u8) as char;
+        table[offset + 27] = (b'A' + offset as u8) as char;
+        offset += 1;
+    }
+    return table;
+}
+
+// Priority of each ASCII character, worked out once at compile time. Anything that isn't a letter maps to 0.
+const PRIORITY_TABLE: [u8; 128] = build_priority_table();
+// The reverse of PRIORITY_TABLE, from priority back to item type
+const ITEM_TYPE_TABLE: [char; 64] = build_item_type_table();
+
+impl Rucksack<'_> {
+    // Fails on the first character that isn't an item type under the scheme
+    fn new<'a, S: PriorityScheme>(contents: &'a str, line_number: usize, scheme: &S) -> ResultOrErr<Rucksack<'a>> {
+        let pouch_size = contents.chars().count() / 2;
+        let mut first_compartment = ItemSet::default();
+        let mut second_compartment = ItemSet::default();
+        for (column, item_type) in contents.chars().enumerate() {
+            let priority = match scheme.priority(item_type) {
+                Some(x) => x,
+                None => return Err(format!("Line {}, column {}: unknown item type '{}'", line_number, column + 1, item_type))
+            };
+            if column < pouch_size {
+                first_compartment.insert(priority);
+            } else {
+                second_compartment.insert(priority);
+            }
+        }
+
+        return Ok(Rucksack{line_number, pouch_size, contents, first_compartment, second_compartment});
+    }
+
+    fn items(&self) -> ItemSet {
+        return self.first_compartment.union(self.second_compartment);
+    }
+}
+
+// Every item type found in both compartments
+fn get_incorrect_item_types(rucksack: Rucksack) -> ItemSet
+{
+    return rucksack.first_compartment.intersection(rucksack.second_compartment);
+}
 
+// Every item type carried by all the elves in the group
+fn get_common_item_types(elf_group: &ElfGroup) -> ItemSet
+{
+    return elf_group.iter().fold(ItemSet(u64::MAX), |common, rucksack| common.intersection(rucksack.items()));
+}
+
+fn get_incorrect_item_type<S: PriorityScheme>(rucksack: Rucksack, strict: bool, scheme: &S) -> ResultOrErr<ItemType>
+{
+    let incorrect = get_incorrect_item_types(rucksack);
+    if strict && incorrect.len() > 1 {
+        return Err(format!("Line {}: more than one item type in both compartments: {}", rucksack.line_number, format_items(incorrect, scheme)));
+    }
+
+    return match incorrect.first(scheme) {
+        Some(item) => Ok(item),
+        None => Err(format!("Line {}: no duplicate item found!", rucksack.line_number))
+    };
+}
+
+fn get_common_item_type<S: PriorityScheme>(elf_group: &ElfGroup, strict: bool, scheme: &S) -> ResultOrErr<ItemType>
+{
+    let common = get_common_item_types(elf_group);
+    if strict && common.len() > 1 {
+        return Err(format!("{}: more than one common item type: {}", describe_group(elf_group), format_items(common, scheme)));
+    }
+
+    return match common.first(scheme) {
+        Some(item) => Ok(item),
+        None => Err(format!("{}: no common item found!", describe_group(elf_group)))
+    };
+}
+
+fn format_items<S: PriorityScheme>(items: ItemSet, scheme: &S) -> String {
+    return items.item_types(scheme).iter().map(|item| item.to_string()).collect::<Vec<String>>().join(", ");
+}
+
+fn describe_group(elf_group: &ElfGroup) -> String {
+    let line_numbers: Vec<String> = elf_group.iter().map(|rucksack| rucksack.line_number.to_string()).collect();
+    return format!("Group on lines {}", line_numbers.join(", "));
+}
+
+fn split_into_groups<'a, 'b>(rucksacks: &'b [Rucksack<'a>], group_size: usize) -> ResultOrErr<Vec<&'b ElfGroup<'a>>> {
+    if !rucksacks.len().is_multiple_of(group_size) {
+        return Err(format!(
+            "{} rucksacks can't be split into groups of {}, {} would be left over",
+            rucksacks.len(), group_size, rucksacks.len() % group_size
+        ));
+    }
+
+    return Ok(rucksacks.chunks(group_size).collect());
+}
+
+
+fn get_item_type_priority<S: PriorityScheme>(item_type: ItemType, scheme: &S) -> ResultOrErr<i32>
+{
+    return match scheme.priority(item_type) {
+        Some(x) => Ok(x as i32),
+        None => Err(format!("'{}' isn't an item type", item_type))
+    };
+}
 
-fn get_incorrect_item_type(rucksack: Rucksack) -> ResultOrErr<ItemType>
+fn parse_input_as_rucksacks<'a, S: PriorityScheme>(input: Lines<'a>, scheme: &S) -> ResultOrErr<Vec<Rucksack<'a>>>
+{
+    let mut output: Vec<Rucksack> = Vec::new();
+    for (index, line) in input.enumerate() {
+        if line.is_empty() {
+            continue
+        }
+        if line.chars().count() % 2 != 0 {
+            return Err(format!("Line {}: {} items can't be split evenly between two compartments", index + 1, line.chars().count()));
+        }
+        output.push(Rucksack::new(line, index + 1, scheme)?);
+    }
+
+    return Ok(output);
+}
+
+fn solve_a<S: PriorityScheme>(input_filename: &str, options: &Options, scheme: &S) -> ResultOrErr<i32> {
+    let input: String = load_input(input_filename)?;
+    let rucksacks = parse_input_as_rucksacks(input.lines(), scheme)?;
+
+    let mut priority_sum = 0;
+
+    for rucksack in rucksacks {
+        let item_type = get_incorrect_item_type(rucksack, options.strict, scheme)?;
+        priority_sum += get_item_type_priority(item_type, scheme)?;
+    }
+
+    return Ok(priority_sum);
+}
+
+fn solve_b<S: PriorityScheme>(input_filename: &str, options: &Options, scheme: &S) -> ResultOrErr<i32> {
+    let input: String = load_input(input_filename)?;
+    let rucksacks = parse_input_as_rucksacks(input.lines(), scheme)?;
+
+    let mut priority_sum = 0;
+    for elf_group in split_into_groups(&rucksacks, options.group_size)? {
+        let item_type = get_common_item_type(elf_group, options.strict, scheme)?;
+        priority_sum += get_item_type_priority(item_type, scheme)?;
+    }
+
+    return Ok(priority_sum);
+}
+
+// Lists every shared item type per rucksack and per group, rather than just the first
+fn report<S: PriorityScheme>(input_filename: &str, options: &Options, scheme: &S) -> ResultOrErr<String> {
+    let input: String = load_input(input_filename)?;
+    let rucksacks = parse_input_as_rucksacks(input.lines(), scheme)?;
+
+    let mut output: Vec<String> = Vec::new();
+    for rucksack in &rucksacks {
+        if options.strict {
+            get_incorrect_item_type(*rucksack, true, scheme)?;
+        }
+        output.push(format!("Line {}: in both compartments: {}", rucksack.line_number, format_items(get_incorrect_item_types(*rucksack), scheme)));
+    }
+    for elf_group in split_into_groups(&rucksacks, options.group_size)? {
+        if options.strict {
+            get_common_item_type(elf_group, true, scheme)?;
+        }
+        output.push(format!("{}: common to all: {}", describe_group(elf_group), format_items(get_common_item_types(elf_group), scheme)));
+    }
+
+    return Ok(output.join("\n"));
+}
+
+// Gives up on group inference after this many search steps rather than running forever
+const GROUP_SEARCH_STEP_LIMIT: usize = 1_000_000;
+
+// Every set of `group_size` rucksacks (as indexes into `rucksacks`) that shares exactly one item type
+fn candidate_groups(rucksacks: &[Rucksack], group_size: usize) -> Vec<Vec<usize>> {
+    let mut output: Vec<Vec<usize>> = Vec::new();
+    let mut current: Vec<usize> = Vec::new();
+    extend_candidate_group(rucksacks, group_size, 0, ItemSet(u64::MAX), &mut current, &mut output);
+    return output;
+}
+
+fn extend_candidate_group(
+    rucksacks: &[Rucksack],
+    group_size: usize,
+    start: usize,
+    common: ItemSet,
+    current: &mut Vec<usize>,
+    output: &mut Vec<Vec<usize>>
+) {
+    if current.len() == group_size {
+        if common.len() == 1 {
+            output.push(current.clone());
+        }
+        return;
+    }
+    for next in start..rucksacks.len() {
+        let narrowed = common.intersection(rucksacks[next].items());
+        if narrowed.len() == 0 {
+            // Adding more rucksacks can only shrink the common items further
+            continue
+        }
+        current.push(next);
+        extend_candidate_group(rucksacks, group_size, next + 1, narrowed, current, output);
+        current.pop();
+    }
+}
+
+// Exact cover search (Knuth's Algorithm X) for a set of candidate groups that uses every rucksack once.
+// Always branches on the rucksack with the fewest groups left, and stops after finding two partitions
+// since that's enough to know whether the answer is unique.
+struct GroupSearch<'a> {
+    groups: &'a [Vec<usize>],
+    groups_containing: Vec<Vec<usize>>,
+    group_alive: Vec<bool>,
+    live_group_count: Vec<usize>,
+    covered: Vec<bool>,
+    chosen: Vec<usize>,
+    solutions: Vec<Vec<usize>>,
+    steps: usize,
+    step_limit: usize
+}
+
+impl GroupSearch<'_> {
+    fn new(groups: &[Vec<usize>], rucksack_count: usize, step_limit: usize) -> GroupSearch<'_> {
+        let mut groups_containing: Vec<Vec<usize>> = vec![Vec::new(); rucksack_count];
+        for (group_index, group) in groups.iter().enumerate() {
+            for rucksack in group {
+                groups_containing[*rucksack].push(group_index);
+            }
+        }
+        let live_group_count = groups_containing.iter().map(|containing| containing.len()).collect();
+
+        return GroupSearch{
+            groups,
+            groups_containing,
+            group_alive: vec![true; groups.len()],
+            live_group_count,
+            covered: vec![false; rucksack_count],
+            chosen: Vec::new(),
+            solutions: Vec::new(),
+            steps: 0,
+            step_limit
+        };
+    }
+
+    fn search(&mut self) {
+        if self.solutions.len() >= 2 || self.steps >= self.step_limit {
+            return;
+        }
+        self.steps += 1;
+
+        let next_rucksack = (0..self.covered.len())
+            .filter(|rucksack| !self.covered[*rucksack])
+            .min_by_key(|rucksack| self.live_group_count[*rucksack]);
+        let rucksack = match next_rucksack {
+            Some(x) => x,
+            None => {
+                self.solutions.push(self.chosen.clone());
+                return;
+            }
+        };
+
+        // Try the groups whose other members have the fewest alternatives first, as they're the
+        // hardest rucksacks to place later on
+        let mut options: Vec<usize> = self.groups_containing[rucksack].iter()
+            .copied()
+            .filter(|group| self.group_alive[*group])
+            .collect();
+        options.sort_by_key(|group| self.groups[*group].iter().map(|member| self.live_group_count[*member]).sum::<usize>());
+
+        for group in options {
+            if !self.group_alive[group] {
+                continue
+            }
+            let removed = self.choose_group(group);
+            self.chosen.push(group);
+            self.search();
+            self.chosen.pop();
+            self.unchoose_group(group, removed);
+        }
+    }
+
+    // Covers the group's rucksacks and kills every other group that uses any of them
+    fn choose_group(&mut self, group: usize) -> Vec<usize> {
+        let mut removed: Vec<usize> = Vec::new();
+        for rucksack in &self.groups[group] {
+            self.covered[*rucksack] = true;
+            for other in &self.groups_containing[*rucksack] {
+                if self.group_alive[*other] {
+                    self.group_alive[*other] = false;
+                    for member in &self.groups[*other] {
+                        self.live_group_count[*member] -= 1;
+                    }
+                    removed.push(*other);
+                }
+            }
+        }
+        return removed;
+    }
+
+    fn unchoose_group(&mut self, group: usize, removed: Vec<usize>) {
+        for other in removed.iter().rev() {
+            self.group_alive[*other] = true;
+            for member in &self.groups[*other] {
+                self.live_group_count[*member] += 1;
+            }
+        }
+        for rucksack in &self.groups[group] {
+            self.covered[*rucksack] = false;
+        }
+    }
+}
+
+// Finds groups for rucksacks given in any order, where every group shares exactly one item type
+fn infer_groups<S: PriorityScheme>(input_filename: &str, options: &Options, scheme: &S) -> ResultOrErr<String> {
+    let input: String = load_input(input_filename)?;
+    return infer_groups_in(&input, options, scheme, GROUP_SEARCH_STEP_LIMIT);
+}
+
+fn infer_groups_in<S: PriorityScheme>(input: &str, options: &Options, scheme: &S, step_limit: usize) -> ResultOrErr<String> {
+    let rucksacks = parse_input_as_rucksacks(input.lines(), scheme)?;
+    split_into_groups(&rucksacks, options.group_size)?;
+
+    let groups = candidate_groups(&rucksacks, options.group_size);
+    let mut search = GroupSearch::new(&groups, rucksacks.len(), step_limit);
+    search.search();
+
+    let gave_up = search.steps >= step_limit;
+    let solution = match search.solutions.first() {
+        Some(x) => x,
+        None if gave_up => return Err(format!("Gave up after {} search steps without finding a grouping", search.steps)),
+        None => return Err("No grouping gives every group exactly one shared item type".to_string())
+    };
+
+    let mut chosen_groups: Vec<Vec<Rucksack>> = solution.iter()
+        .map(|group| groups[*group].iter().map(|rucksack| rucksacks[*rucksack]).collect())
+        .collect();
+    chosen_groups.sort_by_key(|group| group[0].line_number);
+
+    let mut output: Vec<String> = Vec::new();
+    let mut priority_sum = 0;
+    for elf_group in &chosen_groups {
+        let badge = get_common_item_type(elf_group, true, scheme)?;
+        priority_sum += get_item_type_priority(badge, scheme)?;
+        output.push(format!("{}: badge {}", describe_group(elf_group), badge));
+    }
+    output.push(format!("Badge priority sum: {}", priority_sum));
+    output.push(if search.solutions.len() > 1 {
+        "The grouping is not unique".to_string()
+    } else if gave_up {
+        format!("Gave up after {} search steps, so the grouping may not be unique", search.steps)
+    } else {
+        "The grouping is unique".to_string()
+    });
+
+    return Ok(output.join("\n"));
+}
+
+// A swap of two items, each given as (index of rucksack in its group, position in the rucksack)
+struct ItemSwap {
+    first: (usize, usize),
+    second: (usize, usize)
+}
+
+// The fewest swaps between the two compartments that leave no item type in both, as pairs of positions.
+// Each item type has to end up wholly in one compartment, so this picks the set of types for the first
+// compartment that exactly fills it while pulling in as few items from the second as possible (a small
+// knapsack over item types). None when no set of types fills the first compartment exactly.
+fn plan_compartment_swaps(items: &[ItemType]) -> Option<Vec<(usize, usize)>> {
+    let pouch_size = items.len() / 2;
+    let (first, second) = items.split_at(pouch_size);
+
+    let mut types: Vec<ItemType> = items.to_vec();
+    types.sort();
+    types.dedup();
+    let first_counts: Vec<usize> = types.iter().map(|item| first.iter().filter(|x| *x == item).count()).collect();
+    let second_counts: Vec<usize> = types.iter().map(|item| second.iter().filter(|x| *x == item).count()).collect();
+
+    // best[t][c] is the fewest items pulled in from the second compartment when the first t types
+    // put c items in the first compartment
+    let mut best: Vec<Vec<Option<usize>>> = vec![vec![None; pouch_size + 1]; types.len() + 1];
+    best[0][0] = Some(0);
+    for t in 0..types.len() {
+        let size = first_counts[t] + second_counts[t];
+        for c in 0..=pouch_size {
+            let Some(cost) = best[t][c] else { continue };
+            // This type goes to the second compartment
+            if best[t + 1][c].is_none_or(|x| cost < x) {
+                best[t + 1][c] = Some(cost);
+            }
+            // This type goes to the first compartment
+            if c + size <= pouch_size && best[t + 1][c + size].is_none_or(|x| cost + second_counts[t] < x) {
+                best[t + 1][c + size] = Some(cost + second_counts[t]);
+            }
+        }
+    }
+    best[types.len()][pouch_size]?;
+
+    // Walk back through the table to find which types went where
+    let mut in_first: Vec<bool> = vec![false; types.len()];
+    let mut c = pouch_size;
+    for t in (0..types.len()).rev() {
+        let size = first_counts[t] + second_counts[t];
+        let cost = best[t + 1][c].unwrap();
+        if best[t][c] != Some(cost) {
+            in_first[t] = true;
+            c -= size;
+        }
+    }
+
+    let belongs_in_first = |item: &ItemType| in_first[types.binary_search(item).unwrap()];
+    let leaving_first: Vec<usize> = (0..pouch_size).filter(|position| !belongs_in_first(&items[*position])).collect();
+    let leaving_second: Vec<usize> = (pouch_size..items.len()).filter(|position| belongs_in_first(&items[*position])).collect();
+
+    return Some(leaving_first.into_iter().zip(leaving_second).collect());
+}
+
+// Greedy repair for a rucksack that can't be fixed on its own: looks for one swap between it and another member
+// of its group after which both can be repacked, choosing the one that adds the fewest swaps to the pair. Only
+// one swap is considered at a time and the rucksacks are repaired one after another, so the total is a heuristic
+// and not the fewest swaps for the whole group.
+fn plan_repair_swap(contents: &[Vec<ItemType>], rucksack: usize) -> Option<ItemSwap> {
+    let mut best: Option<(isize, ItemSwap)> = None;
+    for other in 0..contents.len() {
+        if other == rucksack {
+            continue
+        }
+        let other_cost_before = plan_compartment_swaps(&contents[other]).map_or(0, |swaps| swaps.len());
+        for position in 0..contents[rucksack].len() {
+            for other_position in 0..contents[other].len() {
+                if contents[rucksack][position] == contents[other][other_position] {
+                    continue
+                }
+                let mut swapped = contents[rucksack].clone();
+                let mut other_swapped = contents[other].clone();
+                std::mem::swap(&mut swapped[position], &mut other_swapped[other_position]);
+                let (Some(swaps), Some(other_swaps)) = (plan_compartment_swaps(&swapped), plan_compartment_swaps(&other_swapped)) else {
+                    continue
+                };
+                // The other rucksack's swaps were already needed if it could be repacked before, so only count the change
+                let cost = (1 + swaps.len() + other_swaps.len()) as isize - other_cost_before as isize;
+                if best.as_ref().is_none_or(|(best_cost, _)| cost < *best_cost) {
+                    best = Some((cost, ItemSwap{first: (rucksack, position), second: (other, other_position)}));
+                }
+            }
+        }
+    }
+
+    return best.map(|(_, swap)| swap);
+}
+
+// Plans the fewest item swaps so that no rucksack has an item type in both compartments. With --group-repair,
+// rucksacks that can't be fixed on their own are repaired with swaps from their group and the total becomes a
+// heuristic.
+fn repack<S: PriorityScheme>(input_filename: &str, options: &Options, scheme: &S) -> ResultOrErr<String> {
+    let input: String = load_input(input_filename)?;
+    let rucksacks = parse_input_as_rucksacks(input.lines(), scheme)?;
+    let groups: Vec<&ElfGroup> = if options.group_repair {
+        split_into_groups(&rucksacks, options.group_size)?
+    } else {
+        rucksacks.chunks(1).collect()
+    };
+
+    let mut output: Vec<String> = Vec::new();
+    let mut total_swaps = 0;
+    let mut repaired = false;
+    for elf_group in groups {
+        let mut contents: Vec<Vec<ItemType>> = elf_group.iter().map(|rucksack| rucksack.contents.chars().collect()).collect();
+
+        for index in 0..elf_group.len() {
+            if plan_compartment_swaps(&contents[index]).is_some() {
+                continue
+            }
+            let rucksack = elf_group[index];
+            if !options.group_repair {
+                return Err(format!("Line {}: no way to separate the item types by swapping within the rucksack", rucksack.line_number));
+            }
+            let swap = match plan_repair_swap(&contents, index) {
+                Some(x) => x,
+                None => return Err(format!("Line {}: no single swap with the rest of its group lets it be repacked", rucksack.line_number))
+            };
+            let first_item = contents[swap.first.0][swap.first.1];
+            let second_item = contents[swap.second.0][swap.second.1];
+            contents[swap.first.0][swap.first.1] = second_item;
+            contents[swap.second.0][swap.second.1] = first_item;
+            output.push(format!(
+                "Line {} position {} ({}) <-> line {} position {} ({})",
+                elf_group[swap.first.0].line_number, swap.first.1 + 1, first_item,
+                elf_group[swap.second.0].line_number, swap.second.1 + 1, second_item
+            ));
+            total_swaps += 1;
+            repaired = true;
+        }
+
+        for (index, rucksack) in elf_group.iter().enumerate() {
+            let swaps = plan_compartment_swaps(&contents[index]).unwrap();
+            total_swaps += swaps.len();
+            let descriptions: Vec<String> = swaps.iter()
+                .map(|(from, to)| format!("{} ({}) <-> {} ({})", from + 1, contents[index][*from], to + 1, contents[index][*to]))
+                .collect();
+            output.push(if descriptions.is_empty() {
+                format!("Line {}: no swaps needed", rucksack.line_number)
+            } else {
+                format!("Line {}: swap {}", rucksack.line_number, descriptions.join(", "))
+            });
+        }
+    }
+    output.push(if repaired {
+        format!("Total swaps: {} (group repair is a heuristic, so this may not be the fewest)", total_swaps)
+    } else {
+        format!("Total swaps: {}", total_swaps)
+    });
+
+    return Ok(output.join("\n"));
+}
+
+// The original HashMap-based lookups, kept so the benchmark has something to compare against
+fn get_incorrect_item_type_hashmap(rucksack: Rucksack) -> ResultOrErr<ItemType>
 {
     let mut seen: HashMap<ItemType, bool> = HashMap::new();
-    let mut index = 0;
 
-    for item in rucksack.contents.chars() {
+    for (index, item) in rucksack.contents.chars().enumerate() {
         if index < rucksack.pouch_size {
             // We're in the first pouch - record items seen
             seen.insert(item, true);
-        } else {
-            // We're in the second pouch - look for items that we saw in the first
-            if seen.contains_key(&item) {
-                return Ok(item);
-            }
+        } else if seen.contains_key(&item) {
+            // We're in the second pouch and saw this item in the first
+            return Ok(item);
         }
-        index += 1;
     }
 
     return Err("No duplicate item found!".to_string());
 }
 
-fn get_common_item_type(elf_group: ElfGroup) -> ResultOrErr<ItemType>
+fn get_common_item_type_hashmap(elf_group: &ElfGroup) -> ResultOrErr<ItemType>
 {
     let mut seen: HashMap<ItemType, i32> = HashMap::new();
 
-    for item in (&elf_group[0]).contents.chars() {
+    for item in elf_group[0].contents.chars() {
         // Record all items the first elf has
         seen.insert(item, 1);
     }
 
-    for item in (&elf_group[1]).contents.chars() {
+    for item in elf_group[1].contents.chars() {
         // Record all items common to first and second elf
         if seen.contains_key(&item) {
             seen.insert(item, 2);
         }
     }
 
-    for item in (&elf_group[2]).contents.chars() {
+    for item in elf_group[2].contents.chars() {
         if seen.get(&item) == Some(&2)  {
             // Item is common to all three elfs
             return Ok(item);
@@ -77,67 +786,102 @@ fn get_common_item_type(elf_group: ElfGroup) -> ResultOrErr<ItemType>
     return Err("No common item found!".to_string())
 }
 
+// Seeded generator for the benchmark's rucksacks
+struct XorShift {
+    state: u64
+}
 
-fn get_item_type_priority(item_type: ItemType) -> i32
-{
-    let code_point =  item_type as i32;
-    return if item_type.is_uppercase() {
-        let reference_point = 'A' as i32;
-        code_point - reference_point + 27
-    } else {
-        let reference_point = 'a' as i32;
-        code_point - reference_point + 1
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        return self.state;
+    }
+
+    fn below(&mut self, limit: usize) -> usize {
+        return (self.next() % limit as u64) as usize;
     }
 }
 
-fn parse_input_as_rucksacks(input: Lines) -> Vec<Rucksack>
-{
-    let mut output: Vec<Rucksack> = Vec::new();
-    for line in input {
-        if line == "" {
-            continue
+// Builds `line_count` rucksacks (rounded down to whole groups) shaped like the puzzle's: every rucksack
+// has exactly one item type in both compartments and every group of three shares exactly one badge
+fn generate_rucksacks(line_count: usize, rng: &mut XorShift) -> String {
+    let mut output = String::new();
+    for _group in 0..(line_count / 3) {
+        let mut item_types: Vec<u8> = (1..=52).collect();
+        for index in (1..item_types.len()).rev() {
+            item_types.swap(index, rng.below(index + 1));
+        }
+        let badge = item_types[0];
+        // Give each elf its own 17 item types, so the badge is the only thing the three have in common
+        for pool in item_types[1..].chunks(17) {
+            let duplicate = pool[0];
+            let (first_pool, second_pool) = pool[1..].split_at(8);
+            let pouch_size = 2 + rng.below(23);
+
+            let mut first: Vec<u8> = vec![badge, duplicate];
+            let mut second: Vec<u8> = vec![duplicate];
+            while first.len() < pouch_size {
+                first.push(first_pool[rng.below(first_pool.len())]);
+            }
+            while second.len() < pouch_size {
+                second.push(second_pool[rng.below(second_pool.len())]);
+            }
+            for priority in first.iter().chain(second.iter()) {
+                output.push(ITEM_TYPE_TABLE[*priority as usize]);
+            }
+            output.push('\n');
         }
-        let rucksack = Rucksack{contents: line, pouch_size: line.len() / 2};
-        output.push(rucksack);
     }
 
     return output;
 }
 
-fn solve_a(input_filename: &str) -> ResultOrErr<i32> {
-    let input: String = load_input(input_filename)?;
-    let rucksacks = parse_input_as_rucksacks(input.lines());
+// Times the HashMap and bitset lookups against each other on the same generated rucksacks
+fn bench(line_count_arg: &str) -> ResultOrErr<String> {
+    let line_count = match line_count_arg.parse::<usize>() {
+        Ok(x) => x,
+        Err(_) => return Err(format!("--bench takes a line count, got '{}'", line_count_arg))
+    };
+    let mut rng = XorShift{state: 0x2022_1203};
+    let input = generate_rucksacks(line_count, &mut rng);
 
-    let mut priority_sum = 0;
+    let start = Instant::now();
+    let rucksacks = parse_input_as_rucksacks(input.lines(), &DefaultScheme)?;
+    let parse_time = start.elapsed();
 
-    for rucksack in rucksacks {
-        let item_type = get_incorrect_item_type(rucksack)?;
-        priority_sum += get_item_type_priority(item_type);
+    let mut hashmap_sums = (0, 0);
+    let start = Instant::now();
+    for rucksack in &rucksacks {
+        hashmap_sums.0 += get_item_type_priority(get_incorrect_item_type_hashmap(*rucksack)?, &DefaultScheme)?;
     }
+    for group in rucksacks.chunks_exact(3) {
+        hashmap_sums.1 += get_item_type_priority(get_common_item_type_hashmap(group)?, &DefaultScheme)?;
+    }
+    let hashmap_time = start.elapsed();
 
-    return Ok(priority_sum);
-}
-
-fn solve_b(input_filename: &str) -> ResultOrErr<i32> {
-    let input: String = load_input(input_filename)?;
-    let rucksacks = parse_input_as_rucksacks(input.lines());
-
-    let mut elf_group : ElfGroup = [Rucksack{contents: "", pouch_size: 0}; 3];
-    let mut current_group_size = 0;
-    let mut priority_sum = 0;
-    for rucksack in rucksacks {
-        elf_group[current_group_size] = rucksack;
-        current_group_size += 1;
-
-        if current_group_size == 3 {
-            let item_type = get_common_item_type(elf_group)?;
-            priority_sum += get_item_type_priority(item_type);
+    let mut bitset_sums = (0, 0);
+    let start = Instant::now();
+    for rucksack in &rucksacks {
+        bitset_sums.0 += get_item_type_priority(get_incorrect_item_type(*rucksack, false, &DefaultScheme)?, &DefaultScheme)?;
+    }
+    for group in rucksacks.chunks_exact(3) {
+        bitset_sums.1 += get_item_type_priority(get_common_item_type(group, false, &DefaultScheme)?, &DefaultScheme)?;
+    }
+    let bitset_time = start.elapsed();
 
-            current_group_size = 0;
-        }
+    if hashmap_sums != bitset_sums {
+        return Err(format!("HashMap lookups gave {:?} but bitsets gave {:?}", hashmap_sums, bitset_sums));
     }
 
-    return Ok(priority_sum);
+    // The HashMap lookups do all their work from the raw strings, so charge the bitsets for building them too
+    let bitset_total_time = parse_time + bitset_time;
+    return Ok(format!(
+        "{} rucksacks (answers {} and {})\nHashMap lookups: {:?}\nbitset parsing: {:?}\nbitset lookups: {:?}\nspeedup including parsing: {:.1}x",
+        rucksacks.len(), bitset_sums.0, bitset_sums.1, hashmap_time, parse_time, bitset_time,
+        hashmap_time.as_secs_f64() / bitset_total_time.as_secs_f64().max(f64::EPSILON)
+    ));
 }
 
 fn load_input(input_filename: &str) -> ResultOrErr<String> {
@@ -146,3 +890,167 @@ fn load_input(input_filename: &str) -> ResultOrErr<String> {
         Err(x) => Err(x.to_string())
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::collections::VecDeque;
+
+    fn is_separated(items: &[ItemType]) -> bool {
+        let (first, second) = items.split_at(items.len() / 2);
+        return !first.iter().any(|item| second.contains(item));
+    }
+
+    // Breadth first search over every arrangement reachable by swapping an item in the first compartment with one
+    // in the second. Returns the fewest swaps that separate the item types, or None if no arrangement does.
+    fn brute_force_swap_count(items: &[ItemType]) -> Option<usize> {
+        let pouch_size = items.len() / 2;
+        let mut seen: HashSet<Vec<ItemType>> = HashSet::from([items.to_vec()]);
+        let mut queue: VecDeque<(Vec<ItemType>, usize)> = VecDeque::from([(items.to_vec(), 0)]);
+        while let Some((arrangement, swaps)) = queue.pop_front() {
+            if is_separated(&arrangement) {
+                return Some(swaps);
+            }
+            for first in 0..pouch_size {
+                for second in pouch_size..arrangement.len() {
+                    let mut next = arrangement.clone();
+                    next.swap(first, second);
+                    if seen.insert(next.clone()) {
+                        queue.push_back((next, swaps + 1));
+                    }
+                }
+            }
+        }
+        return None;
+    }
+
+    #[test]
+    fn compartment_swaps_match_brute_force() {
+        let mut rng = XorShift{state: 0x2022_1203};
+        for _rucksack in 0..300 {
+            let length = 2 * (1 + rng.below(4));
+            let type_count = 1 + rng.below(4);
+            let items: Vec<ItemType> = (0..length).map(|_| (b'a' + rng.below(type_count) as u8) as char).collect();
+
+            let expected = brute_force_swap_count(&items);
+            let planned = plan_compartment_swaps(&items);
+            assert_eq!(planned.as_ref().map(|swaps| swaps.len()), expected, "items {:?}", items);
+            if let Some(swaps) = planned {
+                let mut repacked = items.clone();
+                for (first, second) in swaps {
+                    assert!(first < items.len() / 2 && second >= items.len() / 2);
+                    repacked.swap(first, second);
+                }
+                assert!(is_separated(&repacked), "items {:?} repacked as {:?}", items, repacked);
+            }
+        }
+    }
+
+    #[test]
+    fn compartment_swaps_need_an_exact_fill() {
+        // Three a's can't all go in a compartment of two, nor share one with the b
+        assert!(plan_compartment_swaps(&['a', 'a', 'a', 'b']).is_none());
+        assert_eq!(plan_compartment_swaps(&['a', 'b', 'a', 'b']).map(|swaps| swaps.len()), Some(1));
+        assert_eq!(plan_compartment_swaps(&['a', 'b', 'c', 'd']), Some(Vec::new()));
+    }
+
+    const EXAMPLE: &str = include_str!("../../exampleInput.txt");
+
+    fn options(group_size: usize) -> Options {
+        return Options{group_size, strict: false, group_repair: false, alphabet: None};
+    }
+
+    fn verdict(output: &str) -> &str {
+        return output.lines().last().unwrap();
+    }
+
+    // Two groups, one sharing a and the other sharing e, with nothing else shared across them
+    const SHUFFLED_GROUPS: &str = "efzz\nabyy\negxx\nacww\nehvv\nadtt";
+
+    #[test]
+    fn inferred_grouping_is_unique() {
+        let output = infer_groups_in(SHUFFLED_GROUPS, &options(3), &DefaultScheme, GROUP_SEARCH_STEP_LIMIT).unwrap();
+        assert_eq!(output, [
+            "Group on lines 1, 3, 5: badge e",
+            "Group on lines 2, 4, 6: badge a",
+            "Badge priority sum: 6",
+            "The grouping is unique"
+        ].join("\n"));
+    }
+
+    #[test]
+    fn inferred_grouping_is_not_unique() {
+        // x is shared by 1, 2, 3 and 6 and y by 3, 4, 5 and 6, so 3 and 6 can swap groups
+        let input = "xa\nxc\nxy\nyb\nyd\nyx";
+        let output = infer_groups_in(input, &options(3), &DefaultScheme, GROUP_SEARCH_STEP_LIMIT).unwrap();
+        assert_eq!(verdict(&output), "The grouping is not unique");
+    }
+
+    #[test]
+    fn inferred_grouping_gives_up_at_the_step_limit() {
+        assert_eq!(
+            infer_groups_in(SHUFFLED_GROUPS, &options(3), &DefaultScheme, 1),
+            Err("Gave up after 1 search steps without finding a grouping".to_string())
+        );
+        let output = infer_groups_in(SHUFFLED_GROUPS, &options(3), &DefaultScheme, 3).unwrap();
+        assert_eq!(verdict(&output), "Gave up after 3 search steps, so the grouping may not be unique");
+    }
+
+    #[test]
+    fn inferred_grouping_needs_whole_groups() {
+        let lines: Vec<&str> = EXAMPLE.lines().take(5).collect();
+        assert_eq!(
+            infer_groups_in(&lines.join("\n"), &options(3), &DefaultScheme, GROUP_SEARCH_STEP_LIMIT),
+            Err("5 rucksacks can't be split into groups of 3, 2 would be left over".to_string())
+        );
+    }
+
+    #[test]
+    fn item_type_priorities() {
+        assert_eq!(get_item_type_priority('a', &DefaultScheme), Ok(1));
+        assert_eq!(get_item_type_priority('Z', &DefaultScheme), Ok(52));
+        assert_eq!(get_item_type_priority('1', &DefaultScheme), Err("'1' isn't an item type".to_string()));
+        let scheme = AlphabetScheme::parse("xyz").unwrap();
+        assert_eq!(get_item_type_priority('z', &scheme), Ok(3));
+        assert_eq!(get_item_type_priority('a', &scheme), Err("'a' isn't an item type".to_string()));
+    }
+
+    #[test]
+    fn alphabet_parse_errors() {
+        assert_eq!(
+            AlphabetScheme::parse("abc\nd ea").err(),
+            Some("Alphabet line 2, column 4: 'a' is listed twice".to_string())
+        );
+        assert_eq!(AlphabetScheme::parse("").err(), Some("Alphabet is empty".to_string()));
+        assert_eq!(AlphabetScheme::parse(" \n\t\n").err(), Some("Alphabet is empty".to_string()));
+        let too_long: String = ('\u{100}'..).take(64).collect();
+        assert_eq!(
+            AlphabetScheme::parse(&too_long).err(),
+            Some("Alphabet has 64 item types but at most 63 are supported".to_string())
+        );
+        let longest: String = ('\u{100}'..).take(63).collect();
+        assert!(AlphabetScheme::parse(&longest).is_ok());
+    }
+
+    #[test]
+    fn unknown_item_types_are_reported_with_line_and_column() {
+        let scheme = AlphabetScheme::parse("abcd").unwrap();
+        assert_eq!(
+            parse_input_as_rucksacks("abcd\n\ndcxa\n".lines(), &scheme).err(),
+            Some("Line 3, column 3: unknown item type 'x'".to_string())
+        );
+        assert_eq!(
+            parse_input_as_rucksacks("vJrw\npW1w\n".lines(), &DefaultScheme).err(),
+            Some("Line 2, column 3: unknown item type '1'".to_string())
+        );
+    }
+
+    #[test]
+    fn odd_length_rucksacks_are_rejected() {
+        assert_eq!(
+            parse_input_as_rucksacks("abAB\nabcdefg\n".lines(), &DefaultScheme).err(),
+            Some("Line 2: 7 items can't be split evenly between two compartments".to_string())
+        );
+    }
+}
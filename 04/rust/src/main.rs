@@ -1,5 +1,8 @@
+#![allow(clippy::needless_return)]
+
 use std::env;
 use std::fs;
+use std::fmt;
 use core::str::Lines;
 
 fn main() {
@@ -10,7 +13,14 @@ fn main() {
     let result = match &(&args[1])[..] {
         "--solve-a" => solve_a(input_filename),
         "--solve-b" => solve_b(input_filename),
-        _ => panic!("Must provide --solve-a or --solve-b as first arg")
+        "--coverage" => report_coverage(input_filename),
+        "--groups" => report_groups(input_filename),
+        "--reassign" => reassign_sections(input_filename, args[3..].iter().any(|arg| arg == "--camp")),
+        "--render" => match parse_render_options(&args[3..]) {
+            Ok(options) => render(input_filename, &options),
+            Err(e) => Err(e)
+        },
+        _ => panic!("Must provide --solve-a, --solve-b, --coverage, --groups, --reassign or --render as first arg")
     };
 
     match result {
@@ -20,92 +30,800 @@ fn main() {
 }
 
 type ResultOrErr<X> = Result<X, String>;
-struct ElfAssignment {
-    lower: i32,
-    upper: i32
+
+// Closed range of sections, so both lower and upper are included
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+struct Interval {
+    lower: u64,
+    upper: u64
 }
+type ElfAssignment = Interval;
+
+impl Interval {
+    fn new(lower: u64, upper: u64) -> ResultOrErr<Interval> {
+        if lower > upper {
+            return Err(format!("Range {}-{} is reversed", lower, upper));
+        }
+        return Ok(Interval{lower, upper});
+    }
+
+    // Number of sections covered. Saturates rather than overflowing for the full u64 range.
+    fn len(&self) -> u64 {
+        return (self.upper - self.lower).saturating_add(1);
+    }
 
-impl ElfAssignment {
-    fn fully_contains(&self, other: &ElfAssignment) -> bool {
+    fn fully_contains(&self, other: &Interval) -> bool {
         return self.lower <= other.lower && self.upper >= other.upper;
     }
 
-    fn includes(&self, section:i32) -> bool {
-        return self.lower <= section && self.upper >= section;
+    fn intersects(&self, other: &Interval) -> bool {
+        return self.lower <= other.upper && other.lower <= self.upper;
+    }
+
+    fn intersection(&self, other: &Interval) -> Option<Interval> {
+        if !self.intersects(other) {
+            return None;
+        }
+        return Some(Interval{lower: self.lower.max(other.lower), upper: self.upper.min(other.upper)});
+    }
+
+    fn union(&self, other: &Interval) -> IntervalSet {
+        return IntervalSet::from_intervals(vec![*self, *other]);
+    }
+
+    // Sections in self but not in other
+    fn difference(&self, other: &Interval) -> IntervalSet {
+        return IntervalSet::from_intervals(vec![*self]).difference(&IntervalSet::from_intervals(vec![*other]));
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}-{}", self.lower, self.upper);
+    }
+}
+
+// Set of sections stored as sorted intervals that neither overlap nor touch
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+struct IntervalSet {
+    intervals: Vec<Interval>
+}
+
+impl IntervalSet {
+    // Merges overlapping and touching intervals, so 1-3 and 4-6 become 1-6
+    fn from_intervals(mut intervals: Vec<Interval>) -> IntervalSet {
+        intervals.sort();
+        let mut merged: Vec<Interval> = Vec::with_capacity(intervals.len());
+        for interval in intervals {
+            match merged.last_mut() {
+                Some(last) if interval.lower <= last.upper.saturating_add(1) => {
+                    last.upper = last.upper.max(interval.upper);
+                },
+                _ => merged.push(interval)
+            }
+        }
+        return IntervalSet{intervals: merged};
+    }
+
+    fn len(&self) -> u64 {
+        return self.intervals.iter().fold(0, |total: u64, interval| total.saturating_add(interval.len()));
+    }
+
+    fn is_empty(&self) -> bool {
+        return self.intervals.is_empty();
+    }
+
+    fn intersects(&self, other: &Interval) -> bool {
+        let first_candidate = self.intervals.partition_point(|interval| interval.upper < other.lower);
+        return match self.intervals.get(first_candidate) {
+            Some(interval) => interval.lower <= other.upper,
+            None => false
+        };
+    }
+
+    // Sections in self but not in other
+    fn difference(&self, other: &IntervalSet) -> IntervalSet {
+        let mut output: Vec<Interval> = Vec::new();
+        let mut j = 0;
+        for interval in &self.intervals {
+            let mut remaining = Some(*interval);
+            // Skip the parts of other that end before this interval starts
+            while j < other.intervals.len() && other.intervals[j].upper < interval.lower {
+                j += 1;
+            }
+            let mut k = j;
+            while let (Some(current), Some(cut)) = (remaining, other.intervals.get(k)) {
+                if cut.lower > current.upper {
+                    break;
+                }
+                if cut.lower > current.lower {
+                    output.push(Interval{lower: current.lower, upper: cut.lower - 1});
+                }
+                remaining = if cut.upper < current.upper {
+                    Some(Interval{lower: cut.upper + 1, upper: current.upper})
+                } else {
+                    None
+                };
+                k += 1;
+            }
+            if let Some(current) = remaining {
+                output.push(current);
+            }
+        }
+        return IntervalSet{intervals: output};
     }
+}
 
-    fn intersects(&self, other: &ElfAssignment) -> bool {
-        if self.includes(other.lower) || self.includes(other.upper) {
-            return true;
+impl fmt::Display for IntervalSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.intervals.is_empty() {
+            return write!(f, "none");
         }
-        if self.fully_contains(other) {
-            return true
+        let parts: Vec<String> = self.intervals.iter().map(|interval| interval.to_string()).collect();
+        return write!(f, "{}", parts.join(", "));
+    }
+}
+
+// Elves assigned together on one line of input, there can be any number of them
+struct ElfGroup {
+    line_number: usize,
+    assignments: Vec<ElfAssignment>
+}
+
+impl ElfGroup {
+    // Every pair of elf indexes (i, j) with i < j
+    fn pairs(&self) -> Vec<(usize, usize)> {
+        let mut output: Vec<(usize, usize)> = Vec::new();
+        for i in 0..self.assignments.len() {
+            for j in (i + 1)..self.assignments.len() {
+                output.push((i, j));
+            }
         }
-        if other.fully_contains(self) {
-            return true
+        return output;
+    }
+
+    fn overlap(&self, i: usize, j: usize) -> Option<Interval> {
+        return self.assignments[i].intersection(&self.assignments[j]);
+    }
+
+    fn either_contains(&self, i: usize, j: usize) -> bool {
+        return self.assignments[i].fully_contains(&self.assignments[j]) ||
+            self.assignments[j].fully_contains(&self.assignments[i]);
+    }
+
+    // Sections assigned to every elf in the group
+    fn shared_by_all(&self) -> Option<Interval> {
+        let mut shared = Some(self.assignments[0]);
+        for assignment in &self.assignments[1..] {
+            shared = shared.and_then(|sections| sections.intersection(assignment));
         }
+        return shared;
+    }
+
+    fn contains_all_others(&self, i: usize) -> bool {
+        return self.assignments.iter().all(|assignment| self.assignments[i].fully_contains(assignment));
+    }
+
+    // True if every section of elf i is also assigned to at least one other elf in the group
+    fn covered_by_others(&self, i: usize) -> bool {
+        let others: Vec<Interval> = self.assignments.iter().enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, assignment)| *assignment)
+            .collect();
+        let own = IntervalSet::from_intervals(vec![self.assignments[i]]);
+        return own.difference(&IntervalSet::from_intervals(others)).is_empty();
+    }
 
-        return false;
+    // Splits the overlap graph into connected components, each a sorted list of elf indexes
+    fn overlap_components(&self) -> Vec<Vec<usize>> {
+        let mut component_of: Vec<Option<usize>> = vec![None; self.assignments.len()];
+        let mut components: Vec<Vec<usize>> = Vec::new();
+        for start in 0..self.assignments.len() {
+            if component_of[start].is_some() {
+                continue;
+            }
+            let mut component: Vec<usize> = Vec::new();
+            let mut to_visit = vec![start];
+            component_of[start] = Some(components.len());
+            while let Some(elf) = to_visit.pop() {
+                component.push(elf);
+                for (other, assignment) in self.assignments.iter().enumerate() {
+                    if component_of[other].is_none() && self.assignments[elf].intersects(assignment) {
+                        component_of[other] = Some(components.len());
+                        to_visit.push(other);
+                    }
+                }
+            }
+            component.sort();
+            components.push(component);
+        }
+        return components;
     }
 }
 
-struct ElfPair {
-    assignments: [ElfAssignment; 2]
+impl fmt::Display for ElfGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<String> = self.assignments.iter().map(|assignment| assignment.to_string()).collect();
+        return write!(f, "{}", parts.join(","));
+    }
 }
 
-fn parse_assignment(input: &str) -> ElfAssignment
+fn parse_assignment(input: &str, line_number: usize) -> ResultOrErr<ElfAssignment>
 {
     let parts: Vec<&str> = input.split('-').collect();
-    return ElfAssignment{
-        lower: parts[0].parse::<i32>().unwrap(),
-        upper: parts[1].parse::<i32>().unwrap(),
+    if parts.len() != 2 {
+        return Err(format!("Line {}: expected a range like 2-4, got '{}'", line_number, input));
     }
+    let parse_section = |part: &str| match part.parse::<u64>() {
+        Ok(x) => Ok(x),
+        Err(_) => Err(format!("Line {}: could not parse section '{}'", line_number, part))
+    };
+    return match Interval::new(parse_section(parts[0])?, parse_section(parts[1])?) {
+        Ok(x) => Ok(x),
+        Err(e) => Err(format!("Line {}: {}", line_number, e))
+    };
 }
 
-fn parse_input(input: Lines) -> Vec<ElfPair>
+fn parse_input(input: Lines) -> ResultOrErr<Vec<ElfGroup>>
 {
-    let mut output: Vec<ElfPair> = Vec::new();
-    for line in input {
-        if line == "" {
+    let mut output: Vec<ElfGroup> = Vec::new();
+    for (index, line) in input.enumerate() {
+        if line.is_empty() {
             continue
         }
-        let assignment_strings : Vec<&str> = line.split(',').collect();
-        let elf_pair = ElfPair{assignments: [
-            parse_assignment(assignment_strings[0]), parse_assignment(assignment_strings[1])
-        ]};
-        output.push(elf_pair);
+        let line_number = index + 1;
+        let mut assignments: Vec<ElfAssignment> = Vec::new();
+        for assignment_string in line.split(',') {
+            assignments.push(parse_assignment(assignment_string, line_number)?);
+        }
+        output.push(ElfGroup{line_number, assignments});
     }
 
-    return output;
+    return Ok(output);
 }
 
 fn solve_a(input_filename: &str) -> ResultOrErr<i32> {
     let input: String = load_input(input_filename)?;
-    let elf_pairs = parse_input(input.lines());
+    let elf_groups = parse_input(input.lines())?;
 
-    let mut fully_contained = 0;
-    for elf_pair in elf_pairs {
-        if elf_pair.assignments[0].fully_contains(&elf_pair.assignments[1]) ||
-            elf_pair.assignments[1].fully_contains(&elf_pair.assignments[0]) {
-            fully_contained += 1;
+    let mut groups_with_containment = 0;
+    for elf_group in elf_groups {
+        let mut found = false;
+        for (i, j) in elf_group.pairs() {
+            if elf_group.either_contains(i, j) {
+                // The overlap is the whole of the smaller assignment
+                println!("Line {} elves {} and {}: {},{} overlap {}", elf_group.line_number, i + 1, j + 1,
+                    elf_group.assignments[i], elf_group.assignments[j], elf_group.overlap(i, j).unwrap());
+                found = true;
+            }
+        }
+        if found {
+            groups_with_containment += 1;
         }
     }
 
-    return Ok(fully_contained);
+    return Ok(groups_with_containment);
 }
 
 fn solve_b(input_filename: &str) -> ResultOrErr<i32> {
     let input: String = load_input(input_filename)?;
-    let elf_pairs = parse_input(input.lines());
+    let elf_groups = parse_input(input.lines())?;
+
+    let mut overlapping_groups = 0;
+    for elf_group in &elf_groups {
+        let mut found = false;
+        for (i, j) in elf_group.pairs() {
+            let Some(overlap) = elf_group.overlap(i, j) else {
+                continue;
+            };
+            let (first, second) = (&elf_group.assignments[i], &elf_group.assignments[j]);
+            let mut report = format!("Line {} elves {} and {}: {},{} overlap {} ({} sections)",
+                elf_group.line_number, i + 1, j + 1, first, second, overlap, overlap.len());
+            let only_first = first.difference(second);
+            let only_second = second.difference(first);
+            if !only_first.is_empty() {
+                report += &format!("; only elf {} {}", i + 1, only_first);
+            }
+            if !only_second.is_empty() {
+                report += &format!("; only elf {} {}", j + 1, only_second);
+            }
+            println!("{}; together {}", report, first.union(second));
+            found = true;
+        }
+        if found {
+            overlapping_groups += 1;
+        }
+    }
+
+    return Ok(overlapping_groups);
+}
+
+fn format_elves(elves: &[usize]) -> String {
+    if elves.is_empty() {
+        return "none".to_string();
+    }
+    let parts: Vec<String> = elves.iter().map(|elf| (elf + 1).to_string()).collect();
+    return parts.join(", ");
+}
+
+// Group-wide containment and overlap report for each line. Returns the number of elves whose sections are all
+// covered by the rest of their group.
+fn report_groups(input_filename: &str) -> ResultOrErr<i32> {
+    let input: String = load_input(input_filename)?;
+    let elf_groups = parse_input(input.lines())?;
+
+    let mut covered_elves = 0;
+    for elf_group in &elf_groups {
+        println!("Line {}: {}", elf_group.line_number, elf_group);
+
+        let mut containments: Vec<String> = Vec::new();
+        let mut edges: Vec<String> = Vec::new();
+        for (i, j) in elf_group.pairs() {
+            let (first, second) = (&elf_group.assignments[i], &elf_group.assignments[j]);
+            if first.fully_contains(second) {
+                containments.push(format!("{} contains {}", i + 1, j + 1));
+            } else if second.fully_contains(first) {
+                containments.push(format!("{} contains {}", j + 1, i + 1));
+            }
+            if let Some(overlap) = elf_group.overlap(i, j) {
+                edges.push(format!("{}-{} ({})", i + 1, j + 1, overlap));
+            }
+        }
+        let components: Vec<String> = elf_group.overlap_components().iter()
+            .map(|component| format!("{{{}}}", format_elves(component)))
+            .collect();
+        let contains_all: Vec<usize> = (0..elf_group.assignments.len())
+            .filter(|i| elf_group.assignments.len() > 1 && elf_group.contains_all_others(*i))
+            .collect();
+        let covered: Vec<usize> = (0..elf_group.assignments.len())
+            .filter(|i| elf_group.assignments.len() > 1 && elf_group.covered_by_others(*i))
+            .collect();
+
+        println!("  Containment: {}", if containments.is_empty() { "none".to_string() } else { containments.join(", ") });
+        println!("  Overlap graph: {}", if edges.is_empty() { "none".to_string() } else { edges.join(", ") });
+        println!("  Components: {}", components.join(" "));
+        println!("  Shared by all: {}", match elf_group.shared_by_all() {
+            Some(sections) => sections.to_string(),
+            None => "none".to_string()
+        });
+        println!("  Contains all others: {}", format_elves(&contains_all));
+        println!("  Covered by the others: {}", format_elves(&covered));
+        covered_elves += covered.len() as i32;
+    }
+
+    return Ok(covered_elves);
+}
+
+// Run of consecutive sections that are all covered by the same number of elves
+struct CoverageSegment {
+    sections: Interval,
+    depth: usize
+}
+
+// Sweeps over the start and end points of every assignment to split the span from the lowest to the highest
+// section into segments of constant depth, including depth 0 for gaps. O(n log n) for the sort.
+fn coverage_segments(assignments: &[ElfAssignment]) -> Vec<CoverageSegment> {
+    // Ends are stored one past the upper bound, in u128 so that a range ending at u64::MAX doesn't overflow
+    let mut events: Vec<(u128, i64)> = Vec::with_capacity(assignments.len() * 2);
+    for assignment in assignments {
+        events.push((assignment.lower as u128, 1));
+        events.push((assignment.upper as u128 + 1, -1));
+    }
+    events.sort_unstable();
+
+    let mut segments: Vec<CoverageSegment> = Vec::new();
+    let mut depth: i64 = 0;
+    let mut i = 0;
+    while i < events.len() {
+        let position = events[i].0;
+        while i < events.len() && events[i].0 == position {
+            depth += events[i].1;
+            i += 1;
+        }
+        if i < events.len() {
+            // Every event after the last one closes an assignment, so depth is only 0 here for genuine gaps
+            let sections = Interval{lower: position as u64, upper: (events[i].0 - 1) as u64};
+            segments.push(CoverageSegment{sections, depth: depth as usize});
+        }
+    }
+
+    return segments;
+}
+
+// Given the segments covered by exactly one elf, an assignment is redundant if it doesn't touch any of them
+fn is_redundant(assignment: &ElfAssignment, sole_coverage: &[Interval]) -> bool {
+    let first_candidate = sole_coverage.partition_point(|sections| sections.upper < assignment.lower);
+    return match sole_coverage.get(first_candidate) {
+        Some(sections) => sections.lower > assignment.upper,
+        None => true
+    };
+}
+
+fn report_coverage(input_filename: &str) -> ResultOrErr<i32> {
+    let input: String = load_input(input_filename)?;
+    let elf_groups = parse_input(input.lines())?;
+
+    let assignments: Vec<ElfAssignment> = elf_groups.iter().flat_map(|elf_group| elf_group.assignments.iter().copied()).collect();
+    let segments = coverage_segments(&assignments);
+
+    let covered = IntervalSet::from_intervals(
+        segments.iter().filter(|segment| segment.depth > 0).map(|segment| segment.sections).collect()
+    );
+    let gaps = IntervalSet::from_intervals(
+        segments.iter().filter(|segment| segment.depth == 0).map(|segment| segment.sections).collect()
+    );
+    let max_depth = segments.iter().map(|segment| segment.depth).max().unwrap_or(0);
+    let deepest = IntervalSet::from_intervals(
+        segments.iter().filter(|segment| segment.depth == max_depth).map(|segment| segment.sections).collect()
+    );
+    // Already sorted and disjoint, as the sweep produces segments in order
+    let sole_coverage: Vec<Interval> = segments.iter().filter(|segment| segment.depth == 1).map(|segment| segment.sections).collect();
+
+    println!("Assignments: {}", assignments.len());
+    println!("Distinct sections covered: {} ({})", covered.len(), covered);
+    println!("Maximum depth: {} at {}", max_depth, deepest);
+    println!("Uncovered sections: {} ({})", gaps.len(), gaps);
+
+    // Each elf listed here could be dropped on its own, but two identical assignments are each redundant given
+    // the other, so dropping every listed elf at once may uncover sections
+    let mut redundant_elves = 0;
+    println!("Redundant elves:");
+    for elf_group in &elf_groups {
+        for (elf_index, assignment) in elf_group.assignments.iter().enumerate() {
+            if is_redundant(assignment, &sole_coverage) {
+                println!("  Line {} elf {}: {}", elf_group.line_number, elf_index + 1, assignment);
+                redundant_elves += 1;
+            }
+        }
+    }
+
+    return Ok(redundant_elves);
+}
+
+// Number of sections that differ between two placements of the same elf
+fn sections_changed(old: &Interval, new: &Interval) -> u64 {
+    return old.lower.abs_diff(new.lower).min(old.len()).saturating_mul(2);
+}
+
+// Best way to put left directly before right so that together they still cover every section in covered.
+// The change is piecewise linear in the boundary between them, so it's enough to try the boundaries where
+// either elf stays put or has just moved clear of its old range, plus the ends of the feasible range.
+fn place_adjacent(left: &Interval, right: &Interval, covered: &Interval) -> Option<(u64, Interval, Interval)> {
+    let (left_len, right_len) = (left.len() as i128, right.len() as i128);
+    // Sections are numbered from 1 in the puzzle, so only use section 0 if someone already did
+    let first_section = covered.lower.min(1) as i128;
+    // The boundary is the last section of left
+    let lowest = (covered.upper as i128 - right_len).max(first_section + left_len - 1);
+    let highest = (covered.lower as i128 + left_len - 1).min(u64::MAX as i128 - right_len);
+    if lowest > highest {
+        return None;
+    }
+
+    let left_stays = left.upper as i128;
+    let right_stays = right.lower as i128 - 1;
+    let candidates = [
+        lowest, highest,
+        left_stays, left_stays - left_len, left_stays + left_len,
+        right_stays, right_stays - right_len, right_stays + right_len
+    ];
+
+    let mut best: Option<(u64, Interval, Interval)> = None;
+    for candidate in candidates {
+        let boundary = candidate.clamp(lowest, highest);
+        let new_left = Interval{lower: (boundary - left_len + 1) as u64, upper: boundary as u64};
+        let new_right = Interval{lower: (boundary + 1) as u64, upper: (boundary + right_len) as u64};
+        let cost = sections_changed(left, &new_left) + sections_changed(right, &new_right);
+        if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+            best = Some((cost, new_left, new_right));
+        }
+    }
+    return best;
+}
+
+// Exact fewest-changes placement for two elves. They have to end up next to each other to cover the sections
+// they used to share, unless one covers all of them already, in which case moving the other next to it is as
+// cheap as moving it anywhere else.
+fn reassign_pair(first: &Interval, second: &Interval) -> ResultOrErr<[Interval; 2]> {
+    let covered = match first.union(second).intervals[..] {
+        [sections] if first.intersects(second) => sections,
+        _ => return Ok([*first, *second])
+    };
+    let first_then_second = place_adjacent(first, second, &covered)
+        .map(|(cost, new_first, new_second)| (cost, [new_first, new_second]));
+    let second_then_first = place_adjacent(second, first, &covered)
+        .map(|(cost, new_second, new_first)| (cost, [new_first, new_second]));
+
+    return match (first_then_second, second_then_first) {
+        (Some(a), Some(b)) => Ok(if b.0 < a.0 { b.1 } else { a.1 }),
+        (Some(a), None) => Ok(a.1),
+        (None, Some(b)) => Ok(b.1),
+        (None, None) => Err(format!("No room to separate {} and {}", first, second))
+    };
+}
+
+// Walks the elves in order of lower bound and pushes each one right until it starts after the previous one
+// ends. Never uncovers a section, as anything an elf is pushed off was already covered by the elves before it,
+// but it isn't guaranteed to change the fewest sections.
+fn reassign_push_right(assignments: &[Interval]) -> ResultOrErr<Vec<Interval>> {
+    let mut order: Vec<usize> = (0..assignments.len()).collect();
+    order.sort_by_key(|i| assignments[*i]);
+
+    let mut output = assignments.to_vec();
+    let mut next_free: Option<u64> = None;
+    for i in order {
+        let assignment = &assignments[i];
+        let lower = match next_free {
+            Some(section) => assignment.lower.max(section),
+            None => assignment.lower
+        };
+        let upper = match lower.checked_add(assignment.len() - 1) {
+            Some(x) => x,
+            None => return Err(format!("No room to move {} past section {}", assignment, lower))
+        };
+        output[i] = Interval{lower, upper};
+        next_free = upper.checked_add(1);
+    }
+
+    return Ok(output);
+}
+
+// Also says whether the answer is known to change the fewest sections, which is only the case for pairs
+fn reassign(assignments: &[Interval]) -> ResultOrErr<(Vec<Interval>, bool)> {
+    if assignments.len() == 2 {
+        return Ok((reassign_pair(&assignments[0], &assignments[1])?.to_vec(), true));
+    }
+    return Ok((reassign_push_right(assignments)?, false));
+}
+
+// Confirms that a reassignment kept every workload, removed every overlap and still covers every section the old
+// assignments did. Removing an overlap means someone has to take on sections nobody had before, so returns
+// those extra sections.
+fn check_reassignment(old: &[Interval], new: &[Interval]) -> ResultOrErr<IntervalSet> {
+    for (before, after) in old.iter().zip(new) {
+        if before.len() != after.len() {
+            return Err(format!("Reassigning {} to {} changed its length", before, after));
+        }
+    }
+    let mut sorted = new.to_vec();
+    sorted.sort();
+    for window in sorted.windows(2) {
+        if window[0].intersects(&window[1]) {
+            return Err(format!("Reassignment still overlaps at {} and {}", window[0], window[1]));
+        }
+    }
+    let old_union = IntervalSet::from_intervals(old.to_vec());
+    let new_union = IntervalSet::from_intervals(sorted);
+    let uncovered = old_union.difference(&new_union);
+    if !uncovered.is_empty() {
+        return Err(format!("Reassignment covers {} instead of {}, leaving {} uncovered", new_union, old_union, uncovered));
+    }
+    return Ok(new_union.difference(&old_union));
+}
+
+// Proposes new ranges without any overlap, either within each group or across the whole camp, and prints a diff
+// for every line that changed. Returns the number of elves that were moved.
+fn reassign_sections(input_filename: &str, across_camp: bool) -> ResultOrErr<i32> {
+    let input: String = load_input(input_filename)?;
+    let elf_groups = parse_input(input.lines())?;
+
+    // Each line's new assignments, whether they're known to be minimal and any sections nobody had before
+    let new_groups: Vec<(Vec<Interval>, bool, IntervalSet)> = if across_camp {
+        let assignments: Vec<ElfAssignment> = elf_groups.iter().flat_map(|elf_group| elf_group.assignments.iter().copied()).collect();
+        let mut reassigned = reassign_push_right(&assignments)?;
+        let added = check_reassignment(&assignments, &reassigned)?;
+        if !added.is_empty() {
+            println!("Camp now also covers {} ({} sections)", added, added.len());
+        }
+        let mut output: Vec<(Vec<Interval>, bool, IntervalSet)> = Vec::new();
+        for elf_group in &elf_groups {
+            let rest = reassigned.split_off(elf_group.assignments.len());
+            output.push((reassigned, false, IntervalSet::default()));
+            reassigned = rest;
+        }
+        output
+    } else {
+        let mut output: Vec<(Vec<Interval>, bool, IntervalSet)> = Vec::new();
+        for elf_group in &elf_groups {
+            let (reassigned, minimal) = reassign(&elf_group.assignments)?;
+            let added = check_reassignment(&elf_group.assignments, &reassigned)?;
+            output.push((reassigned, minimal, added));
+        }
+        output
+    };
+
+    let mut moved_elves = 0;
+    let mut changed: u64 = 0;
+    let mut all_minimal = true;
+    for (elf_group, (new_assignments, minimal, added)) in elf_groups.iter().zip(new_groups) {
+        if elf_group.assignments == new_assignments {
+            continue;
+        }
+        all_minimal &= minimal;
+        let new_group = ElfGroup{line_number: elf_group.line_number, assignments: new_assignments};
+        let method = if minimal { "fewest changes" } else { "pushed right, heuristic so may not be the fewest changes" };
+        println!("Line {} ({}):", elf_group.line_number, method);
+        println!("- {}", elf_group);
+        println!("+ {}", new_group);
+        if !added.is_empty() {
+            println!("  now also covers {}", added);
+        }
+        for (before, after) in elf_group.assignments.iter().zip(&new_group.assignments) {
+            if before != after {
+                moved_elves += 1;
+                changed = changed.saturating_add(sections_changed(before, after));
+            }
+        }
+    }
+    if all_minimal {
+        println!("Sections changed: {}", changed);
+    } else {
+        println!("Sections changed: {} (some lines used the push right heuristic, so this may not be the fewest)", changed);
+    }
+
+    return Ok(moved_elves);
+}
+
+struct RenderOptions {
+    window: Option<Interval>,
+    width: u64,
+    colour: bool,
+    svg_filename: Option<String>
+}
+
+const DEFAULT_RENDER_WIDTH: u64 = 80;
+const SVG_WIDTH: f64 = 800.0;
+const SVG_ROW_HEIGHT: f64 = 20.0;
+const SVG_LABEL_WIDTH: f64 = 200.0;
+
+fn parse_render_options(args: &[String]) -> ResultOrErr<RenderOptions> {
+    let mut options = RenderOptions{window: None, width: DEFAULT_RENDER_WIDTH, colour: true, svg_filename: None};
+    let mut i = 0;
+    while i < args.len() {
+        let value = args.get(i + 1);
+        match (&args[i][..], value) {
+            ("--window", Some(value)) => {
+                options.window = Some(match parse_assignment(value, 0) {
+                    Ok(x) => x,
+                    Err(_) => return Err(format!("--window expects a range like 2-8, got '{}'", value))
+                });
+                i += 2;
+            },
+            ("--width", Some(value)) => {
+                options.width = match value.parse::<u64>() {
+                    Ok(x) if x > 0 => x,
+                    _ => return Err(format!("--width expects a positive number, got '{}'", value))
+                };
+                i += 2;
+            },
+            ("--svg", Some(value)) => {
+                options.svg_filename = Some(value.clone());
+                i += 2;
+            },
+            ("--no-colour", _) => {
+                options.colour = false;
+                i += 1;
+            },
+            (option, _) => return Err(format!("Unknown or incomplete render option '{}'", option))
+        }
+    }
+    return Ok(options);
+}
+
+// Sections shared between elf i and anyone else in its group
+fn overlapping_sections(elf_group: &ElfGroup, i: usize) -> IntervalSet {
+    let overlaps: Vec<Interval> = (0..elf_group.assignments.len())
+        .filter(|j| *j != i)
+        .filter_map(|j| elf_group.overlap(i, j))
+        .collect();
+    return IntervalSet::from_intervals(overlaps);
+}
+
+// Draws one elf as a strip like .234..... where each column is a bucket of sections. With one section per column
+// the column shows the last digit of the section number, otherwise # marks any bucket the elf touches.
+fn render_strip(assignment: &ElfAssignment, overlaps: &IntervalSet, window: &Interval, sections_per_column: u64, colour: bool) -> String {
+    let mut strip = String::new();
+    let mut bucket_lower = window.lower as u128;
+    while bucket_lower <= window.upper as u128 {
+        let bucket_upper = (bucket_lower + sections_per_column as u128 - 1).min(window.upper as u128);
+        let bucket = Interval{lower: bucket_lower as u64, upper: bucket_upper as u64};
+        if !assignment.intersects(&bucket) {
+            strip.push('.');
+        } else {
+            let mark = if sections_per_column == 1 {
+                char::from_digit((bucket.lower % 10) as u32, 10).unwrap()
+            } else {
+                '#'
+            };
+            if colour && overlaps.intersects(&bucket) {
+                strip += &format!("\x1b[31m{}\x1b[0m", mark);
+            } else {
+                strip.push(mark);
+            }
+        }
+        bucket_lower = bucket_upper + 1;
+    }
+    return strip;
+}
+
+// Gantt-style chart with one row per elf, overlaps drawn in red over the elf's bar
+fn render_svg(elf_groups: &[ElfGroup], window: &Interval) -> String {
+    let span = window.len() as f64;
+    let x_of = |section: u64| SVG_LABEL_WIDTH + (section - window.lower) as f64 / span * SVG_WIDTH;
+    let width_of = |sections: &Interval| sections.len() as f64 / span * SVG_WIDTH;
+
+    let mut rows: Vec<String> = Vec::new();
+    let mut row = 0;
+    for elf_group in elf_groups {
+        for (i, assignment) in elf_group.assignments.iter().enumerate() {
+            let y = row as f64 * SVG_ROW_HEIGHT;
+            rows.push(format!(
+                "<text x=\"4\" y=\"{:.1}\" font-size=\"12\">Line {} elf {}: {}</text>",
+                y + SVG_ROW_HEIGHT * 0.7, elf_group.line_number, i + 1, assignment
+            ));
+            if let Some(visible) = assignment.intersection(window) {
+                rows.push(format!(
+                    "<rect x=\"{:.2}\" y=\"{:.1}\" width=\"{:.2}\" height=\"{:.1}\" fill=\"steelblue\"/>",
+                    x_of(visible.lower), y + 2.0, width_of(&visible), SVG_ROW_HEIGHT - 4.0
+                ));
+            }
+            for overlap in &overlapping_sections(elf_group, i).intervals {
+                if let Some(visible) = overlap.intersection(window) {
+                    rows.push(format!(
+                        "<rect x=\"{:.2}\" y=\"{:.1}\" width=\"{:.2}\" height=\"{:.1}\" fill=\"crimson\"/>",
+                        x_of(visible.lower), y + 2.0, width_of(&visible), SVG_ROW_HEIGHT - 4.0
+                    ));
+                }
+            }
+            row += 1;
+        }
+        // Leave a gap between groups, as in the puzzle's diagrams
+        row += 1;
+    }
+
+    let height = row as f64 * SVG_ROW_HEIGHT;
+    return format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\">\n<text x=\"{:.0}\" y=\"{:.1}\" font-size=\"12\">Sections {}</text>\n{}\n</svg>\n",
+        SVG_LABEL_WIDTH + SVG_WIDTH, height + SVG_ROW_HEIGHT, SVG_LABEL_WIDTH, height + SVG_ROW_HEIGHT * 0.7, window, rows.join("\n")
+    );
+}
+
+// Draws every assignment as a strip over the window, which defaults to the span of all assignments. Huge
+// windows are scaled down so each column covers several sections. Returns the number of elves drawn.
+fn render(input_filename: &str, options: &RenderOptions) -> ResultOrErr<i32> {
+    let input: String = load_input(input_filename)?;
+    let elf_groups = parse_input(input.lines())?;
+
+    let window = match options.window {
+        Some(window) => window,
+        None => {
+            let assignments = elf_groups.iter().flat_map(|elf_group| elf_group.assignments.iter());
+            match (assignments.clone().map(|a| a.lower).min(), assignments.map(|a| a.upper).max()) {
+                (Some(lower), Some(upper)) => Interval{lower, upper},
+                _ => return Ok(0)
+            }
+        }
+    };
+
+    if let Some(svg_filename) = &options.svg_filename {
+        if let Err(e) = fs::write(svg_filename, render_svg(&elf_groups, &window)) {
+            return Err(format!("Could not write {}: {}", svg_filename, e));
+        }
+    }
+
+    let sections_per_column = window.len().div_ceil(options.width);
+    println!("Sections {}, {} per column", window, sections_per_column);
 
-    let mut overlapping_pairs = 0;
-    for elf_pair in elf_pairs {
-        if elf_pair.assignments[0].intersects(&elf_pair.assignments[1]) {
-            overlapping_pairs += 1;
+    let mut elves = 0;
+    for elf_group in &elf_groups {
+        for (i, assignment) in elf_group.assignments.iter().enumerate() {
+            let overlaps = overlapping_sections(elf_group, i);
+            println!("{}  {}", render_strip(assignment, &overlaps, &window, sections_per_column, options.colour), assignment);
+            elves += 1;
         }
+        println!();
     }
 
-    return Ok(overlapping_pairs);
+    return Ok(elves);
 }
 
 fn load_input(input_filename: &str) -> ResultOrErr<String> {
@@ -114,3 +832,84 @@ fn load_input(input_filename: &str) -> ResultOrErr<String> {
         Err(x) => Err(x.to_string())
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interval(lower: u64, upper: u64) -> Interval {
+        return Interval::new(lower, upper).unwrap();
+    }
+
+    fn set(intervals: &[(u64, u64)]) -> IntervalSet {
+        return IntervalSet{intervals: intervals.iter().map(|(lower, upper)| interval(*lower, *upper)).collect()};
+    }
+
+    #[test]
+    fn union_merges_adjacent_intervals() {
+        assert_eq!(interval(2, 4).union(&interval(5, 7)), set(&[(2, 7)]));
+        assert_eq!(interval(5, 7).union(&interval(2, 4)), set(&[(2, 7)]));
+        assert_eq!(interval(2, 4).union(&interval(6, 7)), set(&[(2, 4), (6, 7)]));
+    }
+
+    #[test]
+    fn from_intervals_merges_overlapping_and_contained() {
+        let merged = IntervalSet::from_intervals(vec![interval(10, 12), interval(1, 3), interval(2, 5), interval(3, 4), interval(13, 13)]);
+        assert_eq!(merged, set(&[(1, 5), (10, 13)]));
+        assert_eq!(merged.len(), 9);
+        assert!(IntervalSet::from_intervals(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn intersection_of_intervals() {
+        assert_eq!(interval(2, 6).intersection(&interval(4, 8)), Some(interval(4, 6)));
+        assert_eq!(interval(5, 7).intersection(&interval(7, 9)), Some(interval(7, 7)));
+        assert_eq!(interval(2, 8).intersection(&interval(3, 7)), Some(interval(3, 7)));
+        assert_eq!(interval(2, 4).intersection(&interval(5, 7)), None);
+    }
+
+    #[test]
+    fn difference_splits_into_two_pieces() {
+        assert_eq!(interval(2, 8).difference(&interval(4, 5)), set(&[(2, 3), (6, 8)]));
+        assert_eq!(interval(2, 8).difference(&interval(2, 5)), set(&[(6, 8)]));
+        assert!(interval(3, 7).difference(&interval(2, 8)).is_empty());
+        assert_eq!(set(&[(1, 10), (20, 30)]).difference(&set(&[(3, 4), (8, 22), (25, 25)])), set(&[(1, 2), (5, 7), (23, 24), (26, 30)]));
+    }
+
+    #[test]
+    fn intervals_ending_at_u64_max() {
+        assert_eq!(interval(0, u64::MAX).len(), u64::MAX);
+        assert_eq!(interval(u64::MAX, u64::MAX).len(), 1);
+        assert_eq!(interval(10, u64::MAX).union(&interval(5, 9)), set(&[(5, u64::MAX)]));
+        assert_eq!(interval(10, u64::MAX).union(&interval(20, u64::MAX)), set(&[(10, u64::MAX)]));
+        assert_eq!(interval(0, u64::MAX).difference(&interval(u64::MAX, u64::MAX)), set(&[(0, u64::MAX - 1)]));
+        assert_eq!(interval(0, u64::MAX).difference(&interval(5, 6)), set(&[(0, 4), (7, u64::MAX)]));
+        assert!(interval(5, u64::MAX).difference(&interval(0, u64::MAX)).is_empty());
+        assert_eq!(interval(5, u64::MAX).intersection(&interval(u64::MAX, u64::MAX)), Some(interval(u64::MAX, u64::MAX)));
+    }
+
+    #[test]
+    fn check_reassignment_rejects_uncovered_sections() {
+        let old = [interval(1, 5), interval(2, 4)];
+        assert!(check_reassignment(&old, &[interval(2, 6), interval(7, 9)]).is_err());
+        assert!(check_reassignment(&old, &[interval(1, 5), interval(5, 7)]).is_err());
+        assert_eq!(check_reassignment(&old, &[interval(1, 5), interval(6, 8)]), Ok(set(&[(6, 8)])));
+    }
+
+    #[test]
+    fn push_right_keeps_the_old_sections_covered() {
+        let old = [interval(3, 3), interval(1, 5), interval(2, 4), interval(20, 21)];
+        let new = reassign_push_right(&old).unwrap();
+        assert_eq!(new, vec![interval(9, 9), interval(1, 5), interval(6, 8), interval(20, 21)]);
+        assert_eq!(check_reassignment(&old, &new), Ok(set(&[(6, 9)])));
+    }
+
+    #[test]
+    fn interval_set_intersects() {
+        let sections = set(&[(1, 3), (10, 12)]);
+        assert!(sections.intersects(&interval(3, 5)));
+        assert!(sections.intersects(&interval(0, 20)));
+        assert!(!sections.intersects(&interval(4, 9)));
+        assert!(!sections.intersects(&interval(13, u64::MAX)));
+    }
+}
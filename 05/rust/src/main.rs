@@ -1,5 +1,13 @@
+#![allow(clippy::needless_return)]
+
 use std::env;
 use std::fs;
+use std::fmt;
+use std::io;
+use std::io::Write;
+use std::cmp::Reverse;
+use std::time::Instant;
+use std::collections::{BinaryHeap, HashMap};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -9,7 +17,23 @@ fn main() {
     let result = match &(&args[1])[..] {
         "--solve-a" => solve_a(input_filename),
         "--solve-b" => solve_b(input_filename),
-        _ => panic!("Must provide --solve-a or --solve-b as first arg")
+        "--solve" => match parse_crane_option(&args[3..]) {
+            Ok(mut crane) => solve_with_crane(input_filename, crane.as_mut()),
+            Err(e) => Err(e)
+        },
+        "--plan" => match parse_plan_options(&args[3..]) {
+            Ok(options) => plan(input_filename, &options),
+            Err(e) => Err(e)
+        },
+        "--check-round-trip" => check_round_trip(input_filename),
+        "--snapshot" => snapshot(input_filename, &args[3..]),
+        "--bench" => bench(input_filename),
+        "--history" => match parse_crane_option(&args[3..]) {
+            Ok(crane) => explore_history(input_filename, crane.as_ref()),
+            Err(e) => Err(e)
+        },
+        "--costs" => report_costs(input_filename, &args[3..]),
+        _ => panic!("Must provide --solve-a, --solve-b, --solve, --plan, --check-round-trip, --snapshot, --bench, --history or --costs as first arg")
     };
 
     match result {
@@ -20,30 +44,81 @@ fn main() {
 
 type ResultOrErr<X> = Result<X, String>;
 
-type Crate = char;
-#[derive(Clone)]
+type Crate = String;
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 struct Stack {
     crates: Vec<Crate>
 }
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 struct CargoArea {
     stacks: Vec<Stack>
 }
 
 impl CargoArea {
-    fn output(&self) {
-        let mut stack_index = 1;
-        for stack in &self.stacks {
-            print!("stack {}: ", stack_index);
-            for some_crate in &stack.crates {
-                print!("[{}] ", some_crate);
+    // Two different stacks borrowed mutably at once, by index from 0
+    fn stack_pair_mut(&mut self, first: usize, second: usize) -> (&mut Stack, &mut Stack) {
+        assert!(first != second);
+        if first < second {
+            let (lower, upper) = self.stacks.split_at_mut(second);
+            return (&mut lower[first], &mut upper[0]);
+        }
+        let (lower, upper) = self.stacks.split_at_mut(first);
+        return (&mut upper[0], &mut lower[second]);
+    }
+
+    fn tops(&self) -> ResultOrErr<String> {
+        let mut output: String = "".to_string();
+        for (stack_index, stack) in (1..).zip(self.stacks.iter()) {
+            match stack.crates.last() {
+                Some(top) => output.push_str(top),
+                None => return Err(format!("Stack {} is empty", stack_index))
             }
-            println!("---");
-            stack_index = stack_index + 1;
         }
+        return Ok(output);
+    }
+
+    // Writes the stacks back out as the puzzle draws them, crates as [X] in columns with the labels underneath.
+    // Columns are widened to fit the longest crate or label, with everything centred in its column.
+    fn to_drawing(&self) -> String {
+        let height = self.stacks.iter().map(|stack| stack.crates.len()).max().unwrap_or(0);
+        let longest_crate = self.stacks.iter().flat_map(|stack| stack.crates.iter()).map(|some_crate| some_crate.chars().count()).max().unwrap_or(1);
+        let width = (longest_crate + 2).max(self.stacks.len().to_string().len());
+        let mut lines: Vec<String> = Vec::new();
+        for level in (0..height).rev() {
+            let cells: Vec<String> = self.stacks.iter().map(|stack| match stack.crates.get(level) {
+                Some(some_crate) => format!("{:^width$}", format!("[{}]", some_crate), width = width),
+                None => " ".repeat(width)
+            }).collect();
+            lines.push(cells.join(" "));
+        }
+        let labels: Vec<String> = (1..=self.stacks.len()).map(|label| format!("{:^width$}", label, width = width)).collect();
+        lines.push(labels.join(" "));
+        return lines.join("\n");
     }
 }
 
 impl Stack {
+    // Moves the top n crates onto other, keeping their order, without going through a temporary Vec
+    fn move_top_n_to(&mut self, n: usize, other: &mut Stack) -> ResultOrErr<()> {
+        if n > self.crates.len() {
+            return Err(format!("Tried to take {} crates from a stack of {}", n, self.crates.len()));
+        }
+        let start = self.crates.len() - n;
+        other.crates.extend(self.crates.drain(start..));
+        return Ok(());
+    }
+
+    // Same as moving the top crate onto other n times over
+    fn move_top_n_reversed_to(&mut self, n: usize, other: &mut Stack) -> ResultOrErr<()> {
+        if n > self.crates.len() {
+            return Err(format!("Tried to take {} crates from a stack of {}", n, self.crates.len()));
+        }
+        let start = self.crates.len() - n;
+        other.crates.extend(self.crates.drain(start..).rev());
+        return Ok(());
+    }
+
+    // The original crate at a time moves, only kept as the baseline for --bench
     fn take_top_n_one_by_one(&mut self, n: i32) -> ResultOrErr<Vec<Crate>> {
         let mut output : Vec<Crate> = Vec::new();
         for _i in 0..n {
@@ -68,143 +143,1161 @@ impl Stack {
     }
 }
 
+#[derive(Clone, PartialEq, Eq, Debug)]
 struct Move {
     crate_count: i32,
     from_stack: usize,
     to_stack: usize
 }
 
-impl Move {
-    fn output(&self) {
-        println!("move {} from {} to {}", self.crate_count, self.from_stack, self.to_stack)
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "move {} from {} to {}", self.crate_count, self.from_stack, self.to_stack);
     }
 }
 
+#[derive(PartialEq, Eq, Debug)]
 struct Problem {
     cargo_area: CargoArea,
     move_list: Vec<Move>
 }
 
+impl Problem {
+    // Writes the problem in the puzzle's input format, so parse_problem reads back exactly the same thing
+    fn serialise(&self) -> String {
+        let mut output = self.cargo_area.to_drawing();
+        output += "\n\n";
+        for elf_move in &self.move_list {
+            output += &format!("{}\n", elf_move);
+        }
+        return output;
+    }
+}
+
+// Finds the stack labels, which have to be 1, 2, 3... in order, and returns the span of character positions each
+// one covers. Crates are matched to stacks by these spans, so the spacing between columns doesn't matter.
+fn parse_label_line(line: &str, line_number: usize) -> ResultOrErr<Vec<(usize, usize)>> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut columns: Vec<(usize, usize)> = Vec::new();
+    let mut position = 0;
+    while position < chars.len() {
+        if chars[position].is_whitespace() {
+            position += 1;
+            continue;
+        }
+        let start = position;
+        while position < chars.len() && !chars[position].is_whitespace() {
+            position += 1;
+        }
+        let label: String = chars[start..position].iter().collect();
+        match label.parse::<usize>() {
+            Ok(x) if x == columns.len() + 1 => columns.push((start, position - 1)),
+            _ => return Err(format!(
+                "Line {}: expected stack label {} but found '{}'", line_number, columns.len() + 1, label
+            ))
+        }
+    }
+    if columns.is_empty() {
+        return Err(format!("Line {}: expected a line of stack labels", line_number));
+    }
+    return Ok(columns);
+}
+
+// Reads one row of the drawing as (stack index, crate) pairs. Each [crate] goes to the stack whose label it sits
+// over, so rows can be ragged or trimmed and crates can be more than one character wide. A crate's centre has to
+// be within its label's span plus half the gap to the next label on each side, and past the outermost labels the
+// crate still has to overlap its label. Anything else is reported rather than guessed at.
+fn parse_crate_row(line: &str, line_number: usize, columns: &[(usize, usize)]) -> ResultOrErr<Vec<(usize, Crate)>> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut row: Vec<(usize, Crate)> = Vec::new();
+    let mut position = 0;
+    while position < chars.len() {
+        if chars[position].is_whitespace() {
+            position += 1;
+            continue;
+        }
+        if chars[position] != '[' {
+            return Err(format!("Line {}, column {}: expected [ but found '{}'", line_number, position + 1, chars[position]));
+        }
+        let start = position;
+        while position < chars.len() && chars[position] != ']' {
+            position += 1;
+        }
+        if position == chars.len() {
+            return Err(format!("Line {}, column {}: crate is missing its closing ]", line_number, start + 1));
+        }
+        let some_crate: Crate = chars[start + 1..position].iter().collect();
+        if some_crate.is_empty() || some_crate.contains(char::is_whitespace) {
+            return Err(format!("Line {}, column {}: '[{}]' isn't a crate", line_number, start + 1, some_crate));
+        }
+        // Compare doubled positions so half gaps stay in whole numbers. The doubled half gap is just the gap.
+        let centre = start + position;
+        let overhang = position - start;
+        let stack_index = match (0..columns.len()).find(|index| {
+            let (label_start, label_end) = columns[*index];
+            let reach_left = if *index == 0 { overhang } else { label_start - columns[*index - 1].1 - 1 };
+            let reach_right = match columns.get(*index + 1) {
+                Some((next_start, _)) => next_start - label_end - 1,
+                None => overhang
+            };
+            return 2 * label_start <= centre + reach_left && centre <= 2 * label_end + reach_right;
+        }) {
+            Some(x) => x,
+            None => return Err(format!(
+                "Line {}, column {}: crate '[{}]' isn't over any stack label", line_number, start + 1, some_crate
+            ))
+        };
+        if row.iter().any(|(index, _)| *index == stack_index) {
+            return Err(format!("Line {}, column {}: second crate over stack {}", line_number, start + 1, stack_index + 1));
+        }
+        row.push((stack_index, some_crate));
+        position += 1;
+    }
+    return Ok(row);
+}
+
+fn parse_move(line: &str, line_number: usize, stack_count: usize) -> ResultOrErr<Move> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let (crate_count, from_stack, to_stack) = match parts[..] {
+        ["move", crate_count, "from", from_stack, "to", to_stack] => (crate_count, from_stack, to_stack),
+        _ => return Err(format!("Line {}: expected 'move N from X to Y' but found '{}'", line_number, line))
+    };
+    let crate_count = match crate_count.parse::<i32>() {
+        Ok(x) if x >= 0 => x,
+        _ => return Err(format!("Line {}: '{}' isn't a number of crates", line_number, crate_count))
+    };
+    let parse_stack = |stack: &str| match stack.parse::<usize>() {
+        Ok(x) if x >= 1 && x <= stack_count => Ok(x),
+        _ => Err(format!("Line {}: stack '{}' isn't between 1 and {}", line_number, stack, stack_count))
+    };
+    return Ok(Move{crate_count, from_stack: parse_stack(from_stack)?, to_stack: parse_stack(to_stack)?});
+}
+
+// The drawing runs up to the first blank line (or the first move), and its last line holds the stack labels.
+// Everything after that is moves.
 fn parse_problem(input: String) -> ResultOrErr<Problem>
 {
-    let cargo_area = CargoArea{stacks: Vec::new()};
-    let mut problem = Problem{cargo_area, move_list: Vec::new()};
-    // First pass - work out how many stacks we have
-    for line in input.lines() {
-        if line == "" {
-            continue
+    let lines: Vec<(usize, &str)> = input.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line))
+        .skip_while(|(_, line)| line.trim().is_empty())
+        .collect();
+    let drawing_length = lines.iter()
+        .take_while(|(_, line)| !line.trim().is_empty() && !line.starts_with("move "))
+        .count();
+    let (drawing, moves) = lines.split_at(drawing_length);
+
+    let (label_line_number, label_line) = match drawing.last() {
+        Some(x) => x,
+        None => return Err("Expected a drawing of the stacks before the moves".to_string())
+    };
+    let columns = parse_label_line(label_line, *label_line_number)?;
+
+    let mut stacks: Vec<Stack> = vec![Stack{crates: Vec::new()}; columns.len()];
+    // Read from the bottom row up, so each crate lands on top of the ones below it
+    for (line_number, line) in drawing[..drawing.len() - 1].iter().rev() {
+        for (stack_index, some_crate) in parse_crate_row(line, *line_number, &columns)? {
+            stacks[stack_index].add_crate(some_crate);
         }
-        if line.starts_with(" 1 ") {
-            for _stack in line.split_whitespace() {
-                problem.cargo_area.stacks.push(
-                    Stack{crates: Vec::new()}
-                )
-            }
+    }
+
+    let mut move_list: Vec<Move> = Vec::new();
+    for (line_number, line) in moves {
+        if line.trim().is_empty() {
             continue
         }
-        if line.starts_with("move ") {
-            let parts: Vec<&str> = line.split_whitespace().collect::<Vec<&str>>();
-            problem.move_list.push(Move{
-                crate_count: parts[1].parse().unwrap(),
-                from_stack: parts[3].parse().unwrap(),
-                to_stack: parts[5].parse().unwrap(),
+        move_list.push(parse_move(line, *line_number, columns.len())?);
+    }
 
-            });
-            continue
+    return Ok(Problem{cargo_area: CargoArea{stacks}, move_list});
+}
+
+// A crane carries out a move as a series of lifts, each picking up some crates from the top of the from stack
+// and putting them down on the to stack in the same order
+trait Crane {
+    fn name(&self) -> String;
+
+    // Number of crates picked up by each lift, in the order the lifts happen
+    fn plan_lifts(&self, elf_move: &Move, cargo_area: &CargoArea) -> ResultOrErr<Vec<i32>>;
+
+    fn make_move(&mut self, elf_move: &Move, cargo_area: &mut CargoArea) -> ResultOrErr<bool> {
+        let lifts = self.plan_lifts(elf_move, cargo_area)?;
+        apply_lifts(&lifts, elf_move, cargo_area)?;
+        return Ok(true)
+    }
+
+    // Anything the crane wants to say once all the moves are done
+    fn report(&self) -> Option<String> {
+        return None;
+    }
+}
+
+// Each lift costs O(1) per crate it carries, as crates are drained straight from one stack onto the other
+fn apply_lifts(lifts: &[i32], elf_move: &Move, cargo_area: &mut CargoArea) -> ResultOrErr<()> {
+    if elf_move.from_stack == elf_move.to_stack {
+        // Every lift puts its crates straight back where they came from
+        let height = cargo_area.stacks[elf_move.from_stack - 1].crates.len();
+        if lifts.iter().sum::<i32>() as usize > height {
+            return Err(format!("Tried to take {} crates from a stack of {}", elf_move.crate_count, height));
         }
+        return Ok(());
+    }
+    let (from_stack, to_stack) = cargo_area.stack_pair_mut(elf_move.from_stack - 1, elf_move.to_stack - 1);
+    if lifts.iter().all(|lift_size| *lift_size == 1) {
+        // Crates lifted one at a time land in reverse order, which can be done in one go
+        return from_stack.move_top_n_reversed_to(lifts.len(), to_stack);
+    }
+    for lift_size in lifts {
+        from_stack.move_top_n_to(*lift_size as usize, to_stack)?;
+    }
+    return Ok(());
+}
+
+// Moves crates one at a time
+struct CrateMover9000 {}
+
+impl Crane for CrateMover9000 {
+    fn name(&self) -> String {
+        return "CrateMover 9000".to_string();
     }
 
-    // Second pass - work out how many stacks we have
-    for line in input.lines() {
-        if line == "" {
-            continue
+    fn plan_lifts(&self, elf_move: &Move, _cargo_area: &CargoArea) -> ResultOrErr<Vec<i32>> {
+        return Ok(vec![1; elf_move.crate_count as usize]);
+    }
+}
+
+// Moves all the crates at once
+struct CrateMover9001 {}
+
+impl Crane for CrateMover9001 {
+    fn name(&self) -> String {
+        return "CrateMover 9001".to_string();
+    }
+
+    fn plan_lifts(&self, elf_move: &Move, _cargo_area: &CargoArea) -> ResultOrErr<Vec<i32>> {
+        return Ok(vec![elf_move.crate_count]);
+    }
+}
+
+// Moves up to capacity crates at once, splitting larger moves into several lifts from the top down
+struct CapacityCrane {
+    capacity: i32
+}
+
+impl Crane for CapacityCrane {
+    fn name(&self) -> String {
+        return format!("crane with capacity {}", self.capacity);
+    }
+
+    fn plan_lifts(&self, elf_move: &Move, _cargo_area: &CargoArea) -> ResultOrErr<Vec<i32>> {
+        let mut lifts: Vec<i32> = Vec::new();
+        let mut remaining = elf_move.crate_count;
+        while remaining > 0 {
+            let lift_size = remaining.min(self.capacity);
+            lifts.push(lift_size);
+            remaining -= lift_size;
         }
-        if line.starts_with(" 1 ") {
-            break
+        return Ok(lifts);
+    }
+}
+
+// Wraps another crane that can't reach the top of stacks taller than max_height
+struct HeightLimitedCrane {
+    inner: Box<dyn Crane>,
+    max_height: usize
+}
+
+impl Crane for HeightLimitedCrane {
+    fn name(&self) -> String {
+        return format!("{} limited to stacks of {}", self.inner.name(), self.max_height);
+    }
+
+    fn plan_lifts(&self, elf_move: &Move, cargo_area: &CargoArea) -> ResultOrErr<Vec<i32>> {
+        let lifts = self.inner.plan_lifts(elf_move, cargo_area)?;
+        let mut height = cargo_area.stacks[elf_move.from_stack - 1].crates.len();
+        for lift_size in &lifts {
+            if height > self.max_height {
+                return Err(format!(
+                    "Can't lift from stack {} at height {}, the crane only reaches {}",
+                    elf_move.from_stack, height, self.max_height
+                ));
+            }
+            height = height.saturating_sub(*lift_size as usize);
+        }
+        return Ok(lifts);
+    }
+
+    // Checks the height, then leaves the move itself to the inner crane so any wrappers inside this one still run
+    fn make_move(&mut self, elf_move: &Move, cargo_area: &mut CargoArea) -> ResultOrErr<bool> {
+        self.plan_lifts(elf_move, cargo_area)?;
+        return self.inner.make_move(elf_move, cargo_area);
+    }
+
+    fn report(&self) -> Option<String> {
+        return self.inner.report();
+    }
+}
+
+// Work done by a crane for one move, or summed over a run. Distance counts one trip between the stacks per lift.
+#[derive(Clone, Copy, Default)]
+struct MoveCost {
+    lifts: usize,
+    crates: usize,
+    distance: usize,
+    peak_height: usize
+}
+
+impl MoveCost {
+    fn add(&mut self, other: &MoveCost) {
+        self.lifts += other.lifts;
+        self.crates += other.crates;
+        self.distance += other.distance;
+        self.peak_height = self.peak_height.max(other.peak_height);
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct StackUsage {
+    moves_out: usize,
+    crates_out: usize,
+    moves_in: usize,
+    crates_in: usize,
+    peak_height: usize
+}
+
+// Wraps another crane and keeps the cost of every move it makes, along with how much each stack was used
+struct CountingCrane {
+    inner: Box<dyn Crane>,
+    per_move: Vec<MoveCost>,
+    total: MoveCost,
+    stacks: Vec<StackUsage>
+}
+
+impl CountingCrane {
+    fn new(inner: Box<dyn Crane>) -> CountingCrane {
+        return CountingCrane{inner, per_move: Vec::new(), total: MoveCost::default(), stacks: Vec::new()};
+    }
+}
+
+impl Crane for CountingCrane {
+    fn name(&self) -> String {
+        return self.inner.name();
+    }
+
+    fn plan_lifts(&self, elf_move: &Move, cargo_area: &CargoArea) -> ResultOrErr<Vec<i32>> {
+        return self.inner.plan_lifts(elf_move, cargo_area);
+    }
+
+    fn make_move(&mut self, elf_move: &Move, cargo_area: &mut CargoArea) -> ResultOrErr<bool> {
+        if self.stacks.is_empty() {
+            // Starting heights count towards the peaks, so take them before the first move
+            self.stacks = cargo_area.stacks.iter()
+                .map(|stack| StackUsage{peak_height: stack.crates.len(), ..StackUsage::default()})
+                .collect();
+            self.total.peak_height = self.stacks.iter().map(|usage| usage.peak_height).max().unwrap_or(0);
+        }
+
+        let lifts = self.plan_lifts(elf_move, cargo_area)?;
+        self.inner.make_move(elf_move, cargo_area)?;
+
+        let crates = lifts.iter().sum::<i32>() as usize;
+        let to_height = cargo_area.stacks[elf_move.to_stack - 1].crates.len();
+        let cost = MoveCost{
+            lifts: lifts.len(),
+            crates,
+            distance: lifts.len() * elf_move.from_stack.abs_diff(elf_move.to_stack),
+            peak_height: to_height
+        };
+
+        let from_usage = &mut self.stacks[elf_move.from_stack - 1];
+        from_usage.moves_out += 1;
+        from_usage.crates_out += crates;
+        let to_usage = &mut self.stacks[elf_move.to_stack - 1];
+        to_usage.moves_in += 1;
+        to_usage.crates_in += crates;
+        to_usage.peak_height = to_usage.peak_height.max(to_height);
+
+        self.total.add(&cost);
+        self.per_move.push(cost);
+        return Ok(true);
+    }
+
+    fn report(&self) -> Option<String> {
+        let counts = format!(
+            "{} made {} lifts carrying {} crates over {} moves, distance {}, peak height {}",
+            self.name(), self.total.lifts, self.total.crates, self.per_move.len(),
+            self.total.distance, self.total.peak_height
+        );
+        return match self.inner.report() {
+            Some(inner_report) => Some(format!("{}\n{}", inner_report, counts)),
+            None => Some(counts)
+        };
+    }
+}
+
+// Builds a crane from a spec like "capacity:3,max-height:10,count". The first part picks the model (9000, 9001
+// or capacity:N) and anything after it wraps that model.
+fn parse_crane(spec: &str) -> ResultOrErr<Box<dyn Crane>> {
+    let mut parts = spec.split(',');
+    let model = parts.next().unwrap_or("");
+    let mut crane: Box<dyn Crane> = match model.split_once(':') {
+        None if model == "9000" => Box::new(CrateMover9000{}),
+        None if model == "9001" => Box::new(CrateMover9001{}),
+        Some(("capacity", capacity)) => match capacity.parse::<i32>() {
+            Ok(x) if x > 0 => Box::new(CapacityCrane{capacity: x}),
+            _ => return Err(format!("Crane capacity must be a positive number, got '{}'", capacity))
+        },
+        _ => return Err(format!("Unknown crane model '{}', expected 9000, 9001 or capacity:N", model))
+    };
+    for modifier in parts {
+        crane = match modifier.split_once(':') {
+            None if modifier == "count" => Box::new(CountingCrane::new(crane)),
+            Some(("max-height", max_height)) => match max_height.parse::<usize>() {
+                Ok(x) => Box::new(HeightLimitedCrane{inner: crane, max_height: x}),
+                Err(_) => return Err(format!("Crane max-height must be a number, got '{}'", max_height))
+            },
+            _ => return Err(format!("Unknown crane modifier '{}', expected count or max-height:N", modifier))
+        };
+    }
+    return Ok(crane);
+}
+
+fn parse_crane_option(args: &[String]) -> ResultOrErr<Box<dyn Crane>> {
+    return match args {
+        [option, spec] if option == "--crane" => parse_crane(spec),
+        [] => Ok(Box::new(CrateMover9000{})),
+        _ => Err("Expected --crane SPEC".to_string())
+    };
+}
+
+fn solve_with_crane(input_filename: &str, crane: &mut dyn Crane) -> ResultOrErr<String> {
+    let input: String = load_input(input_filename)?;
+    let mut problem = parse_problem(input)?;
+
+    for elf_move in problem.move_list {
+        crane.make_move(&elf_move, &mut problem.cargo_area)?;
+    }
+
+    if let Some(report) = crane.report() {
+        println!("{}", report);
+    }
+
+    return problem.cargo_area.tops();
+}
+
+// What the planner is aiming for, either a whole arrangement or just the crate on top of each stack, where None
+// means any crate (or none) will do
+enum PlanTarget {
+    Arrangement(CargoArea),
+    Tops(Vec<Option<Crate>>)
+}
+
+impl PlanTarget {
+    fn is_reached(&self, cargo_area: &CargoArea) -> bool {
+        return match self {
+            PlanTarget::Arrangement(target) => target == cargo_area,
+            PlanTarget::Tops(tops) => tops.iter().zip(&cargo_area.stacks).all(|(top, stack)| match top {
+                Some(wanted) => stack.crates.last() == Some(wanted),
+                None => true
+            })
+        };
+    }
+
+    // Lower bound on the number of moves left, whatever the crane. For an arrangement, every stack holding a
+    // crate that isn't part of its target must be the source of a move, and every stack short of its target must
+    // be the destination of one. For tops, each wrong top needs a move to or from that stack, and a move only
+    // touches two stacks.
+    fn moves_needed(&self, cargo_area: &CargoArea) -> usize {
+        return match self {
+            PlanTarget::Arrangement(target) => {
+                let mut sources = 0;
+                let mut destinations = 0;
+                for (stack, target_stack) in cargo_area.stacks.iter().zip(&target.stacks) {
+                    let common = stack.crates.iter().zip(&target_stack.crates).take_while(|(a, b)| a == b).count();
+                    if common < stack.crates.len() {
+                        sources += 1;
+                    }
+                    if common < target_stack.crates.len() {
+                        destinations += 1;
+                    }
+                }
+                sources.max(destinations)
+            },
+            PlanTarget::Tops(tops) => {
+                let wrong = tops.iter().zip(&cargo_area.stacks).filter(|(top, stack)| match top {
+                    Some(wanted) => stack.crates.last() != Some(wanted),
+                    None => false
+                }).count();
+                wrong.div_ceil(2)
+            }
+        };
+    }
+
+    // Catches targets that can never be reached, rather than searching the whole state space to find out
+    fn validate(&self, cargo_area: &CargoArea) -> ResultOrErr<()> {
+        let mut available: HashMap<Crate, usize> = HashMap::new();
+        for stack in &cargo_area.stacks {
+            for some_crate in &stack.crates {
+                *available.entry(some_crate.clone()).or_insert(0) += 1;
+            }
         }
-        let mut stack_index = 0;
-        for stack in &mut problem.cargo_area.stacks {
-            let string_pos = 1 + (stack_index * 4);
-            let this_crate = line.chars().collect::<Vec<char>>()[string_pos];
-            if this_crate != ' ' {
-                stack.add_crate(this_crate);
+        let mut wanted: HashMap<Crate, usize> = HashMap::new();
+        let stack_count = match self {
+            PlanTarget::Arrangement(target) => {
+                for stack in &target.stacks {
+                    for some_crate in &stack.crates {
+                        *wanted.entry(some_crate.clone()).or_insert(0) += 1;
+                    }
+                }
+                if wanted != available {
+                    return Err("Target arrangement doesn't hold the same crates as the starting one".to_string());
+                }
+                target.stacks.len()
+            },
+            PlanTarget::Tops(tops) => {
+                for some_crate in tops.iter().flatten() {
+                    *wanted.entry(some_crate.clone()).or_insert(0) += 1;
+                }
+                for (some_crate, count) in &wanted {
+                    if available.get(some_crate).copied().unwrap_or(0) < *count {
+                        return Err(format!("Target tops need {} of crate {} but there aren't that many", count, some_crate));
+                    }
+                }
+                tops.len()
             }
-            stack_index += 1;
+        };
+        if stack_count != cargo_area.stacks.len() {
+            return Err(format!("Target has {} stacks but the cargo area has {}", stack_count, cargo_area.stacks.len()));
         }
+        return Ok(());
     }
+}
+
+struct PlanOptions {
+    target: PlanTarget,
+    crane: Box<dyn Crane>
+}
 
-    for stack in &mut problem.cargo_area.stacks {
-        stack.crates.reverse();
+// Upper bound on the number of arrangements the planner will visit before giving up
+const PLANNER_STATE_LIMIT: usize = 2_000_000;
+
+fn parse_plan_options(args: &[String]) -> ResultOrErr<PlanOptions> {
+    let mut target: Option<PlanTarget> = None;
+    let mut crane: Box<dyn Crane> = Box::new(CrateMover9000{});
+    let mut i = 0;
+    while i < args.len() {
+        let value = match args.get(i + 1) {
+            Some(x) => x,
+            None => return Err(format!("{} needs a value", args[i]))
+        };
+        match &args[i][..] {
+            "--target-tops" => {
+                // ? stands for any crate
+                // Single character crates can be run together, longer ones need commas between them
+                let tops: Vec<String> = if value.contains(',') {
+                    value.split(',').map(|top| top.to_string()).collect()
+                } else {
+                    value.chars().map(|top| top.to_string()).collect()
+                };
+                target = Some(PlanTarget::Tops(tops.into_iter().map(|top| if top == "?" { None } else { Some(top) }).collect()));
+            },
+            "--target" => {
+                let target_problem = parse_problem(load_input(value)?)?;
+                target = Some(PlanTarget::Arrangement(target_problem.cargo_area));
+            },
+            "--crane" => {
+                crane = parse_crane(value)?;
+            },
+            option => return Err(format!("Unknown plan option '{}'", option))
+        }
+        i += 2;
     }
+    return match target {
+        Some(target) => Ok(PlanOptions{target, crane}),
+        None => Err("--plan needs --target FILE or --target-tops CRATES".to_string())
+    };
+}
 
-    return Ok(problem);
+// How the planner got to an arrangement: its index in the list of arrangements, the fewest moves found to reach
+// it, and the index of the arrangement and the move it came from
+struct PlanStep {
+    index: usize,
+    distance: usize,
+    reached_by: Option<(usize, Move)>
 }
 
-fn make_move_9000(elf_move: &Move, cargo_area: &mut CargoArea) -> ResultOrErr<bool> {
-    elf_move.output();
+// A* over arrangements, where every move costs 1 whatever the crane does to carry it out. Returns the shortest
+// list of moves that reaches the target.
+fn find_plan(start: &CargoArea, target: &PlanTarget, crane: &dyn Crane) -> ResultOrErr<Vec<Move>> {
+    target.validate(start)?;
 
-    let from_stack = &mut cargo_area.stacks[elf_move.from_stack - 1];
+    let mut areas: Vec<CargoArea> = vec![start.clone()];
+    let mut seen: HashMap<CargoArea, PlanStep> = HashMap::new();
+    seen.insert(start.clone(), PlanStep{index: 0, distance: 0, reached_by: None});
+    let mut frontier: BinaryHeap<Reverse<(usize, usize, usize)>> = BinaryHeap::new();
+    frontier.push(Reverse((target.moves_needed(start), 0, 0)));
 
-    for moved_crate in from_stack.take_top_n_one_by_one(elf_move.crate_count)? {
-        let to_stack = &mut cargo_area.stacks[elf_move.to_stack - 1];
-        to_stack.add_crate(moved_crate);
+    while let Some(Reverse((_, distance, index))) = frontier.pop() {
+        let cargo_area = areas[index].clone();
+        if distance > seen[&cargo_area].distance {
+            continue;
+        }
+        if target.is_reached(&cargo_area) {
+            let mut moves: Vec<Move> = Vec::new();
+            let mut current = cargo_area;
+            while let Some((previous, elf_move)) = &seen[&current].reached_by {
+                moves.push(elf_move.clone());
+                current = areas[*previous].clone();
+            }
+            moves.reverse();
+            return Ok(moves);
+        }
+
+        for from_stack in 1..=cargo_area.stacks.len() {
+            for to_stack in 1..=cargo_area.stacks.len() {
+                if from_stack == to_stack {
+                    continue;
+                }
+                for crate_count in 1..=cargo_area.stacks[from_stack - 1].crates.len() as i32 {
+                    let elf_move = Move{crate_count, from_stack, to_stack};
+                    // Moves the crane can't make, like lifting from too high, just aren't options
+                    let lifts = match crane.plan_lifts(&elf_move, &cargo_area) {
+                        Ok(x) => x,
+                        Err(_) => continue
+                    };
+                    let mut next = cargo_area.clone();
+                    apply_lifts(&lifts, &elf_move, &mut next)?;
+                    if seen.get(&next).is_some_and(|step| step.distance <= distance + 1) {
+                        continue;
+                    }
+                    if areas.len() >= PLANNER_STATE_LIMIT {
+                        return Err(format!("Gave up after visiting {} arrangements", PLANNER_STATE_LIMIT));
+                    }
+                    let next_index = match seen.get(&next) {
+                        Some(step) => step.index,
+                        None => {
+                            areas.push(next.clone());
+                            areas.len() - 1
+                        }
+                    };
+                    frontier.push(Reverse((distance + 1 + target.moves_needed(&next), distance + 1, next_index)));
+                    seen.insert(next, PlanStep{index: next_index, distance: distance + 1, reached_by: Some((index, elf_move))});
+                }
+            }
+        }
     }
 
-    cargo_area.output();
+    return Err("Target can't be reached with this crane".to_string());
+}
+
+// Writes the plan as a puzzle input, the starting drawing followed by the moves, so it can be replayed
+fn plan(input_filename: &str, options: &PlanOptions) -> ResultOrErr<String> {
+    let input: String = load_input(input_filename)?;
+    let problem = parse_problem(input)?;
+
+    let move_list = find_plan(&problem.cargo_area, &options.target, options.crane.as_ref())?;
 
-    return Ok(true)
+    let plan = Problem{cargo_area: problem.cargo_area, move_list};
+    return Ok(plan.serialise().trim_end().to_string());
 }
 
-fn make_move_9001(elf_move: &Move, cargo_area: &mut CargoArea) -> ResultOrErr<bool> {
-    elf_move.output();
+// Seeded generator for the problems used by --check-round-trip and --bench
+struct XorShift {
+    state: u64
+}
 
-    let from_stack = &mut cargo_area.stacks[elf_move.from_stack - 1];
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        return self.state;
+    }
 
-    for moved_crate in from_stack.take_top_n_as_group(elf_move.crate_count)? {
-        let to_stack = &mut cargo_area.stacks[elf_move.to_stack - 1];
-        to_stack.add_crate(moved_crate);
+    fn below(&mut self, limit: usize) -> usize {
+        return (self.next() % limit as u64) as usize;
     }
+}
 
-    cargo_area.output();
+// Random stacks of lettered crates with a list of moves that are all possible under either crane
+fn generate_problem(rng: &mut XorShift) -> Problem {
+    let stack_count = 1 + rng.below(14);
+    let mut stacks: Vec<Stack> = Vec::new();
+    for _stack in 0..stack_count {
+        // Mostly single letters like the puzzle, with the odd longer crate ID to exercise wider columns
+        let crates = (0..rng.below(9)).map(|_| {
+            let length = if rng.below(10) == 0 { 2 + rng.below(3) } else { 1 };
+            (0..length).map(|_| (b'A' + rng.below(26) as u8) as char).collect()
+        }).collect();
+        stacks.push(Stack{crates});
+    }
+    let mut heights: Vec<usize> = stacks.iter().map(|stack| stack.crates.len()).collect();
 
-    return Ok(true)
+    let mut move_list: Vec<Move> = Vec::new();
+    for _move in 0..rng.below(20) {
+        let from_stack = 1 + rng.below(stack_count);
+        let to_stack = 1 + rng.below(stack_count);
+        if heights[from_stack - 1] == 0 || from_stack == to_stack {
+            continue;
+        }
+        let crate_count = 1 + rng.below(heights[from_stack - 1]);
+        heights[from_stack - 1] -= crate_count;
+        heights[to_stack - 1] += crate_count;
+        move_list.push(Move{crate_count: crate_count as i32, from_stack, to_stack});
+    }
+
+    return Problem{cargo_area: CargoArea{stacks}, move_list};
 }
 
+// Checks that parsing and serialising are inverses, first on the given input and then on generated problems
+fn check_round_trip(input_filename: &str) -> ResultOrErr<String> {
+    let input: String = load_input(input_filename)?;
+    let mut problems = vec![parse_problem(input.clone())?];
+    let serialised = problems[0].serialise();
+    if serialised.trim_end() != input.trim_end() {
+        println!("Note: {} isn't in canonical form, so its text won't round trip exactly", input_filename);
+    }
+
+    let mut rng = XorShift{state: 0x2022_1205};
+    for _problem in 0..500 {
+        problems.push(generate_problem(&mut rng));
+    }
+
+    for (index, problem) in problems.iter().enumerate() {
+        let serialised = problem.serialise();
+        let reparsed = parse_problem(serialised.clone())?;
+        if &reparsed != problem {
+            return Err(format!("Problem {} changed after a round trip:\n{}", index, serialised));
+        }
+        if reparsed.serialise() != serialised {
+            return Err(format!("Problem {} serialised differently after a round trip:\n{}", index, serialised));
+        }
+    }
+
+    return Ok(format!("Parse and serialise round tripped on {} problems", problems.len()));
+}
+
+// Replays the first --after K moves with the chosen crane and writes out the state at that point with the moves
+// still to come, so it can be fed straight back in as an input
+fn snapshot(input_filename: &str, args: &[String]) -> ResultOrErr<String> {
+    let mut after: Option<usize> = None;
+    let mut crane: Box<dyn Crane> = Box::new(CrateMover9000{});
+    for pair in args.chunks(2) {
+        match pair {
+            [option, value] if option == "--after" => after = match value.parse::<usize>() {
+                Ok(x) => Some(x),
+                Err(_) => return Err(format!("--after expects a number of moves, got '{}'", value))
+            },
+            [option, value] if option == "--crane" => crane = parse_crane(value)?,
+            _ => return Err(format!("Unknown or incomplete snapshot option '{}'", pair[0]))
+        }
+    }
+    let after = match after {
+        Some(x) => x,
+        None => return Err("--snapshot needs --after K".to_string())
+    };
 
-fn solve_a(input_filename: &str) -> ResultOrErr<String> {
     let input: String = load_input(input_filename)?;
     let mut problem = parse_problem(input)?;
+    if after > problem.move_list.len() {
+        return Err(format!("Can't snapshot after move {}, there are only {}", after, problem.move_list.len()));
+    }
 
-    problem.cargo_area.output();
+    let remaining = problem.move_list.split_off(after);
+    for elf_move in &problem.move_list {
+        let lifts = crane.plan_lifts(elf_move, &problem.cargo_area)?;
+        apply_lifts(&lifts, elf_move, &mut problem.cargo_area)?;
+    }
 
-    for elf_move in problem.move_list {
-        make_move_9000(&elf_move, &mut problem.cargo_area)?;
+    let snapshot = Problem{cargo_area: problem.cargo_area, move_list: remaining};
+    return Ok(snapshot.serialise().trim_end().to_string());
+}
+
+// One executed move, kept as the lifts the crane made so it can be undone or redone without replaying from the
+// start. Lifts are run length encoded as (size, repeats), so a CrateMover 9000 move is a single entry.
+struct MoveDelta {
+    from_stack: usize,
+    to_stack: usize,
+    lifts: Vec<(i32, usize)>
+}
+
+impl MoveDelta {
+    fn new(elf_move: &Move, lifts: &[i32]) -> MoveDelta {
+        let mut runs: Vec<(i32, usize)> = Vec::new();
+        for lift_size in lifts {
+            match runs.last_mut() {
+                Some((size, repeats)) if size == lift_size => *repeats += 1,
+                _ => runs.push((*lift_size, 1))
+            }
+        }
+        return MoveDelta{from_stack: elf_move.from_stack, to_stack: elf_move.to_stack, lifts: runs};
     }
 
-    let mut output: String = "".to_string();
-    for mut stack in problem.cargo_area.stacks {
-        output.push(stack.take_top_n_one_by_one(1)?[0])
+    fn expand_lifts(&self) -> Vec<i32> {
+        return self.lifts.iter().flat_map(|(size, repeats)| std::iter::repeat_n(*size, *repeats)).collect();
     }
 
-    return Ok(output);
+    fn as_move(&self) -> Move {
+        let crate_count = self.lifts.iter().map(|(size, repeats)| size * *repeats as i32).sum();
+        return Move{crate_count, from_stack: self.from_stack, to_stack: self.to_stack};
+    }
+
+    fn apply(&self, cargo_area: &mut CargoArea) -> ResultOrErr<()> {
+        return apply_lifts(&self.expand_lifts(), &self.as_move(), cargo_area);
+    }
+
+    // Taking the same lifts back in the opposite order, last lift first, puts every crate back where it was
+    fn undo(&self, cargo_area: &mut CargoArea) -> ResultOrErr<()> {
+        let mut lifts = self.expand_lifts();
+        lifts.reverse();
+        let reverse_move = Move{crate_count: lifts.iter().sum(), from_stack: self.to_stack, to_stack: self.from_stack};
+        return apply_lifts(&lifts, &reverse_move, cargo_area);
+    }
 }
 
-fn solve_b(input_filename: &str) -> ResultOrErr<String> {
+// The cargo area at some point in the move list, which can step backwards and forwards through it
+struct History {
+    cargo_area: CargoArea,
+    deltas: Vec<MoveDelta>,
+    // Number of moves made to reach cargo_area
+    position: usize,
+    // For each crate label, the moves (counting from 1) that carried a crate with that label
+    crate_moves: HashMap<Crate, Vec<usize>>
+}
+
+impl History {
+    // Runs every move once to record it, then rewinds to the starting arrangement
+    fn record(problem: Problem, crane: &dyn Crane) -> ResultOrErr<History> {
+        let mut cargo_area = problem.cargo_area;
+        let mut deltas: Vec<MoveDelta> = Vec::with_capacity(problem.move_list.len());
+        let mut crate_moves: HashMap<Crate, Vec<usize>> = HashMap::new();
+        for (move_index, elf_move) in problem.move_list.iter().enumerate() {
+            let lifts = crane.plan_lifts(elf_move, &cargo_area)?;
+            let from_crates = &cargo_area.stacks[elf_move.from_stack - 1].crates;
+            let moved = &from_crates[from_crates.len().saturating_sub(elf_move.crate_count as usize)..];
+            for some_crate in moved {
+                let moves = crate_moves.entry(some_crate.clone()).or_default();
+                if moves.last() != Some(&(move_index + 1)) {
+                    moves.push(move_index + 1);
+                }
+            }
+            apply_lifts(&lifts, elf_move, &mut cargo_area)?;
+            deltas.push(MoveDelta::new(elf_move, &lifts));
+        }
+
+        let mut history = History{cargo_area, position: deltas.len(), deltas, crate_moves};
+        history.go_to(0)?;
+        return Ok(history);
+    }
+
+    fn undo(&mut self) -> ResultOrErr<bool> {
+        if self.position == 0 {
+            return Ok(false);
+        }
+        self.deltas[self.position - 1].undo(&mut self.cargo_area)?;
+        self.position -= 1;
+        return Ok(true);
+    }
+
+    fn redo(&mut self) -> ResultOrErr<bool> {
+        if self.position == self.deltas.len() {
+            return Ok(false);
+        }
+        self.deltas[self.position].apply(&mut self.cargo_area)?;
+        self.position += 1;
+        return Ok(true);
+    }
+
+    // Steps from wherever we are now, so nearby moves are cheap to reach
+    fn go_to(&mut self, position: usize) -> ResultOrErr<()> {
+        if position > self.deltas.len() {
+            return Err(format!("There are only {} moves", self.deltas.len()));
+        }
+        while self.position > position {
+            self.undo()?;
+        }
+        while self.position < position {
+            self.redo()?;
+        }
+        return Ok(());
+    }
+
+    // Top crates after the given number of moves, with - for an empty stack. Leaves the position unchanged.
+    fn tops_after(&mut self, position: usize) -> ResultOrErr<String> {
+        let current = self.position;
+        self.go_to(position)?;
+        let tops: Vec<String> = self.cargo_area.stacks.iter()
+            .map(|stack| stack.crates.last().cloned().unwrap_or("-".to_string()))
+            .collect();
+        self.go_to(current)?;
+        return Ok(tops.concat());
+    }
+
+    // The last move at or before the given position that carried a crate with this label
+    fn last_moved(&self, some_crate: &str, position: usize) -> Option<usize> {
+        let moves = self.crate_moves.get(some_crate)?;
+        let count = moves.partition_point(|move_number| *move_number <= position);
+        return if count == 0 { None } else { Some(moves[count - 1]) };
+    }
+}
+
+const HISTORY_HELP: &str = "Commands:
+  undo [N]       step back N moves (default 1)
+  redo [N]       step forward N moves (default 1)
+  goto K         jump to just after move K, 0 is the start
+  show           draw the stacks as they are now
+  tops [K]       top crates now, or after move K
+  last X [K]     when a crate labelled X last moved, up to now or up to move K
+  help           show this list
+  quit           stop";
+
+fn parse_position(value: Option<&&str>, default: usize) -> ResultOrErr<usize> {
+    return match value {
+        Some(value) => match value.parse::<usize>() {
+            Ok(x) => Ok(x),
+            Err(_) => Err(format!("'{}' isn't a number", value))
+        },
+        None => Ok(default)
+    };
+}
+
+fn run_history_command(history: &mut History, line: &str) -> ResultOrErr<String> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let position = history.position;
+    return match parts.first().copied() {
+        Some("undo") => {
+            let target = position.saturating_sub(parse_position(parts.get(1), 1)?);
+            history.go_to(target)?;
+            Ok(format!("At move {}", history.position))
+        },
+        Some("redo") => {
+            let target = (position + parse_position(parts.get(1), 1)?).min(history.deltas.len());
+            history.go_to(target)?;
+            Ok(format!("At move {}", history.position))
+        },
+        Some("goto") => {
+            history.go_to(parse_position(parts.get(1), position)?)?;
+            Ok(format!("At move {}", history.position))
+        },
+        Some("show") => {
+            let last_move = match position {
+                0 => "No moves made yet".to_string(),
+                _ => format!("After {}", history.deltas[position - 1].as_move())
+            };
+            Ok(format!("{}\n{}", last_move, history.cargo_area.to_drawing()))
+        },
+        Some("tops") => history.tops_after(parse_position(parts.get(1), position)?),
+        Some("last") => match parts.get(1) {
+            Some(some_crate) => {
+                let up_to = parse_position(parts.get(2), position)?;
+                Ok(match history.last_moved(some_crate, up_to) {
+                    Some(move_number) => format!("Move {}: {}", move_number, history.deltas[move_number - 1].as_move()),
+                    None => format!("No crate labelled {} moved by move {}", some_crate, up_to)
+                })
+            },
+            None => Err("last needs a crate label".to_string())
+        },
+        Some("help") | None => Ok(HISTORY_HELP.to_string()),
+        Some(command) => Err(format!("Unknown command '{}', try help", command))
+    };
+}
+
+// Interactive stepping through the moves, reading commands from stdin
+fn explore_history(input_filename: &str, crane: &dyn Crane) -> ResultOrErr<String> {
     let input: String = load_input(input_filename)?;
-    let mut problem = parse_problem(input)?;
+    let problem = parse_problem(input)?;
+    let mut history = History::record(problem, crane)?;
 
-    problem.cargo_area.output();
+    println!("{} moves recorded with the {}, type help for commands", history.deltas.len(), crane.name());
+    loop {
+        print!("move {}/{}> ", history.position, history.deltas.len());
+        if io::stdout().flush().is_err() {
+            break;
+        }
+        let mut line = String::new();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {},
+            Err(e) => return Err(e.to_string())
+        }
+        if line.trim() == "quit" {
+            break;
+        }
+        match run_history_command(&mut history, &line) {
+            Ok(output) => println!("{}", output),
+            Err(e) => println!("Error: {}", e)
+        }
+    }
 
-    for elf_move in problem.move_list {
-        make_move_9001(&elf_move, &mut problem.cargo_area)?;
+    return Ok(format!("Stopped at move {} of {}", history.position, history.deltas.len()));
+}
+
+fn format_saving(baseline: usize, value: usize) -> String {
+    if baseline == 0 {
+        return "-".to_string();
+    }
+    return format!("{:.0}%", (baseline as f64 - value as f64) / baseline as f64 * 100.0);
+}
+
+// Compares the CrateMover 9000 and 9001, plus any crane given with --crane, on the same moves. --per-move adds a
+// line for every move.
+fn report_costs(input_filename: &str, args: &[String]) -> ResultOrErr<String> {
+    let mut cranes: Vec<Box<dyn Crane>> = vec![Box::new(CrateMover9000{}), Box::new(CrateMover9001{})];
+    let mut per_move = false;
+    let mut i = 0;
+    while i < args.len() {
+        match (&args[i][..], args.get(i + 1)) {
+            ("--crane", Some(spec)) => {
+                cranes.push(parse_crane(spec)?);
+                i += 2;
+            },
+            ("--per-move", _) => {
+                per_move = true;
+                i += 1;
+            },
+            (option, _) => return Err(format!("Unknown or incomplete costs option '{}'", option))
+        }
+    }
+
+    let input: String = load_input(input_filename)?;
+    let problem = parse_problem(input)?;
+    // Every crane runs the moves on its own copy of the cargo area, counted by the same CountingCrane as --crane
+    // SPEC,count uses
+    let mut reports: Vec<CountingCrane> = Vec::new();
+    for crane in cranes {
+        let mut counting = CountingCrane::new(crane);
+        let mut cargo_area = problem.cargo_area.clone();
+        for elf_move in &problem.move_list {
+            counting.make_move(elf_move, &mut cargo_area)?;
+        }
+        reports.push(counting);
+    }
+
+    let mut output: Vec<String> = Vec::new();
+    for report in &reports {
+        output.push(format!("{}:", report.name()));
+        if per_move {
+            for (elf_move, cost) in problem.move_list.iter().zip(&report.per_move) {
+                output.push(format!(
+                    "  {}: {} lifts, {} crates, distance {}, height {}",
+                    elf_move, cost.lifts, cost.crates, cost.distance, cost.peak_height
+                ));
+            }
+        }
+        for (stack_index, usage) in (1..).zip(&report.stacks) {
+            output.push(format!(
+                "  stack {}: {} moves out ({} crates), {} moves in ({} crates), peak height {}",
+                stack_index, usage.moves_out, usage.crates_out, usage.moves_in, usage.crates_in, usage.peak_height
+            ));
+        }
+    }
+
+    // Totals side by side, with how much each crane saves against the CrateMover 9000
+    let baseline = reports[0].total;
+    let name_width = reports.iter().map(|report| report.name().len()).max().unwrap_or(0);
+    output.push(format!(
+        "{:<width$}  {:>10} {:>8} {:>10} {:>8} {:>12}",
+        "", "lifts", "saved", "distance", "saved", "peak height", width = name_width
+    ));
+    for report in &reports {
+        output.push(format!(
+            "{:<width$}  {:>10} {:>8} {:>10} {:>8} {:>12}",
+            report.name(), report.total.lifts, format_saving(baseline.lifts, report.total.lifts),
+            report.total.distance, format_saving(baseline.distance, report.total.distance),
+            report.total.peak_height, width = name_width
+        ));
+    }
+    output.push(format!("Every crane moved {} crates over {} moves", baseline.crates, problem.move_list.len()));
+
+    return Ok(output.join("\n"));
+}
+
+fn solve_a(input_filename: &str) -> ResultOrErr<String> {
+    return solve_with_crane(input_filename, &mut CrateMover9000{});
+}
+
+fn solve_b(input_filename: &str) -> ResultOrErr<String> {
+    return solve_with_crane(input_filename, &mut CrateMover9001{});
+}
+
+// Lots of stacks and a long list of moves that are all possible under either crane
+fn generate_bench_problem(stack_count: usize, crate_count: usize, move_count: usize, rng: &mut XorShift) -> Problem {
+    let mut stacks: Vec<Stack> = vec![Stack{crates: Vec::new()}; stack_count];
+    for _crate in 0..crate_count {
+        let some_crate = ((b'A' + rng.below(26) as u8) as char).to_string();
+        stacks[rng.below(stack_count)].add_crate(some_crate);
+    }
+    let mut heights: Vec<usize> = stacks.iter().map(|stack| stack.crates.len()).collect();
+
+    let mut move_list: Vec<Move> = Vec::with_capacity(move_count);
+    while move_list.len() < move_count {
+        let from_stack = 1 + rng.below(stack_count);
+        let to_stack = 1 + rng.below(stack_count);
+        if heights[from_stack - 1] == 0 || from_stack == to_stack {
+            continue;
+        }
+        let crate_count = 1 + rng.below(heights[from_stack - 1].min(64));
+        heights[from_stack - 1] -= crate_count;
+        heights[to_stack - 1] += crate_count;
+        move_list.push(Move{crate_count: crate_count as i32, from_stack, to_stack});
+    }
+
+    return Problem{cargo_area: CargoArea{stacks}, move_list};
+}
+
+// The original make_move_9000 and make_move_9001, minus printing the whole cargo area after every move, which
+// would swamp everything else
+fn legacy_make_move(elf_move: &Move, cargo_area: &mut CargoArea, as_group: bool) -> ResultOrErr<bool> {
+    let from_stack = &mut cargo_area.stacks[elf_move.from_stack - 1];
+    let moved_crates = if as_group {
+        from_stack.take_top_n_as_group(elf_move.crate_count)?
+    } else {
+        from_stack.take_top_n_one_by_one(elf_move.crate_count)?
+    };
+    for moved_crate in moved_crates {
+        let to_stack = &mut cargo_area.stacks[elf_move.to_stack - 1];
+        to_stack.add_crate(moved_crate);
     }
+    return Ok(true);
+}
+
+// Runs a generated move list through the original crate at a time moves and the bulk moves for both cranes
+fn bench(move_count_arg: &str) -> ResultOrErr<String> {
+    let move_count = match move_count_arg.parse::<usize>() {
+        Ok(x) => x,
+        Err(_) => return Err(format!("--bench takes a move count, got '{}'", move_count_arg))
+    };
+    let mut rng = XorShift{state: 0x2022_1205};
+    let problem = generate_bench_problem(100, 10_000, move_count, &mut rng);
+    let crates_moved: usize = problem.move_list.iter().map(|elf_move| elf_move.crate_count as usize).sum();
+
+    let mut report = format!("{} moves carrying {} crates between {} stacks", move_count, crates_moved, problem.cargo_area.stacks.len());
+    let cranes: [(Box<dyn Crane>, bool); 2] = [(Box::new(CrateMover9000{}), false), (Box::new(CrateMover9001{}), true)];
+    for (mut crane, as_group) in cranes {
+        let mut legacy_area = problem.cargo_area.clone();
+        let start = Instant::now();
+        for elf_move in &problem.move_list {
+            legacy_make_move(elf_move, &mut legacy_area, as_group)?;
+        }
+        let legacy_time = start.elapsed();
+
+        let mut bulk_area = problem.cargo_area.clone();
+        let start = Instant::now();
+        for elf_move in &problem.move_list {
+            crane.make_move(elf_move, &mut bulk_area)?;
+        }
+        let bulk_time = start.elapsed();
 
-    let mut output: String = "".to_string();
-    for mut stack in problem.cargo_area.stacks {
-        output.push(stack.take_top_n_one_by_one(1)?[0])
+        if legacy_area != bulk_area {
+            return Err(format!("{} ended up with different stacks from the crate at a time moves", crane.name()));
+        }
+        report += &format!(
+            "\n{}: crate at a time {:?}, bulk {:?}, speedup {:.1}x",
+            crane.name(), legacy_time, bulk_time, legacy_time.as_secs_f64() / bulk_time.as_secs_f64().max(f64::EPSILON)
+        );
     }
 
-    return Ok(output);
+    return Ok(report);
 }
 
 fn load_input(input_filename: &str) -> ResultOrErr<String> {
@@ -213,3 +1306,121 @@ fn load_input(input_filename: &str) -> ResultOrErr<String> {
         Err(x) => Err(x.to_string())
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = include_str!("../../exampleInput.txt");
+
+    // Runs the example through the crane and returns the top crates and what the crane reports afterwards
+    fn run_crane(spec: &str) -> (String, String) {
+        let mut crane = parse_crane(spec).unwrap();
+        let mut problem = parse_problem(EXAMPLE.to_string()).unwrap();
+        for elf_move in &problem.move_list {
+            crane.make_move(elf_move, &mut problem.cargo_area).unwrap();
+        }
+        return (problem.cargo_area.tops().unwrap(), crane.report().unwrap());
+    }
+
+    #[test]
+    fn modifiers_count_the_same_in_any_order() {
+        for model in ["9000", "9001", "capacity:2"] {
+            let (count_inside_tops, count_inside) = run_crane(&format!("{},count,max-height:10", model));
+            let (count_outside_tops, count_outside) = run_crane(&format!("{},max-height:10,count", model));
+            assert_eq!(count_inside_tops, count_outside_tops);
+            // The crane names differ with the order, the counts after them must not
+            let counts_only = |report: &str| report.split_once(" made ").unwrap().1.to_string();
+            assert_eq!(counts_only(&count_inside), counts_only(&count_outside));
+        }
+        assert!(run_crane("9000,count,max-height:10").1.ends_with("made 7 lifts carrying 7 crates over 4 moves, distance 10, peak height 4"));
+    }
+
+    fn stack(crates: &[&str]) -> Stack {
+        return Stack{crates: crates.iter().map(|some_crate| some_crate.to_string()).collect()};
+    }
+
+    fn assert_round_trips(problem: &Problem) {
+        let serialised = problem.serialise();
+        let reparsed = parse_problem(serialised.clone()).unwrap();
+        assert_eq!(&reparsed, problem, "changed after a round trip:\n{}", serialised);
+        assert_eq!(reparsed.serialise(), serialised);
+    }
+
+    #[test]
+    fn example_round_trips() {
+        let problem = parse_problem(EXAMPLE.to_string()).unwrap();
+        assert_round_trips(&problem);
+        assert_eq!(problem.serialise().trim_end(), EXAMPLE.trim_end());
+    }
+
+    #[test]
+    fn multi_character_crates_round_trip() {
+        let problem = Problem{
+            cargo_area: CargoArea{stacks: vec![stack(&["AB", "C"]), stack(&["XYZW"]), stack(&["D", "EF", "G"])]},
+            move_list: vec![Move{crate_count: 2, from_stack: 1, to_stack: 3}]
+        };
+        assert_round_trips(&problem);
+    }
+
+    #[test]
+    fn empty_stacks_round_trip() {
+        for (stacks, move_list) in [
+            (vec![stack(&[]), stack(&["A"]), stack(&[])], vec![Move{crate_count: 1, from_stack: 2, to_stack: 1}]),
+            (vec![stack(&["A", "B"]), stack(&[]), stack(&["C"])], vec![Move{crate_count: 2, from_stack: 1, to_stack: 2}]),
+            (vec![stack(&[]), stack(&[])], Vec::new())
+        ] {
+            assert_round_trips(&Problem{cargo_area: CargoArea{stacks}, move_list});
+        }
+    }
+
+    #[test]
+    fn parses_right_trimmed_rows() {
+        let trimmed: Vec<&str> = EXAMPLE.lines().map(|line| line.trim_end()).collect();
+        assert_eq!(parse_problem(trimmed.join("\n")).unwrap(), parse_problem(EXAMPLE.to_string()).unwrap());
+    }
+
+    #[test]
+    fn parses_ten_or_more_stacks_at_puzzle_spacing() {
+        let crates: Vec<String> = (0..12).map(|index| format!("[{}]", (b'A' + index) as char)).collect();
+        let labels: Vec<String> = (1..=12).map(|label| format!("{:^3}", label)).collect();
+        let input = format!("{}\n{}\n\nmove 1 from 12 to 10\n", crates.join(" "), labels.join(" "));
+        let problem = parse_problem(input).unwrap();
+        assert_eq!(problem.cargo_area.tops().unwrap(), "ABCDEFGHIJKL");
+        assert_eq!(problem.move_list, vec![Move{crate_count: 1, from_stack: 12, to_stack: 10}]);
+    }
+
+    #[test]
+    fn rejects_crates_away_from_their_labels() {
+        assert_eq!(
+            parse_problem("[A]                 [B]\n 1   2 \n\nmove 1 from 1 to 2\n".to_string()),
+            Err("Line 1, column 21: crate '[B]' isn't over any stack label".to_string())
+        );
+        // Right between two labels could be either stack
+        assert_eq!(
+            parse_problem("  [A]\n 1   2\n\nmove 1 from 1 to 2\n".to_string()),
+            Err("Line 1, column 3: crate '[A]' isn't over any stack label".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_moves_outside_the_stacks() {
+        let drawing = "[A] [B]\n 1   2\n\n";
+        assert_eq!(
+            parse_problem(format!("{}move 1 from 1 to 2\nmove 1 from 3 to 1\n", drawing)),
+            Err("Line 5: stack '3' isn't between 1 and 2".to_string())
+        );
+        assert_eq!(
+            parse_problem(format!("{}move 1 from 0 to 1\n", drawing)),
+            Err("Line 4: stack '0' isn't between 1 and 2".to_string())
+        );
+    }
+
+    #[test]
+    fn generated_problems_round_trip() {
+        let mut rng = XorShift{state: 0x2022_1205};
+        for _problem in 0..500 {
+            assert_round_trips(&generate_problem(&mut rng));
+        }
+    }
+}
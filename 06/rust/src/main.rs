@@ -1,6 +1,9 @@
+#![allow(clippy::needless_return)]
+
 use std::env;
 use std::fs;
 use std::collections::HashMap;
+use std::time::Instant;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -8,9 +11,17 @@ fn main() {
     let input_filename = &(&args[2])[..];
 
     let result = match &(&args[1])[..] {
-        "--solve-a" => solve_a(input_filename),
-        "--solve-b" => solve_b(input_filename),
-        _ => panic!("Must provide --solve-a or --solve-b as first arg")
+        "--solve-a" => solve_a(input_filename).map(|x| x.to_string()),
+        "--solve-b" => solve_b(input_filename).map(|x| x.to_string()),
+        "--solve" => match &args[3..] {
+            [option, window] if option == "--window" => match window.parse::<usize>() {
+                Ok(window) => solve(input_filename, window).map(|x| x.to_string()),
+                Err(_) => Err(format!("--window expects a number, got '{}'", window))
+            },
+            _ => Err("--solve expects --window K".to_string())
+        },
+        "--bench" => bench(input_filename),
+        _ => panic!("Must provide --solve-a, --solve-b, --solve or --bench as first arg")
     };
 
     match result {
@@ -21,45 +32,132 @@ fn main() {
 
 type ResultOrErr<X> = Result<X, String>;
 
+// Returns how many bytes have been read once the last `window` bytes are all different, or None if that never
+// happens. Keeps a count of each byte value in the window and how many of those counts are above zero, so each
+// step is O(1) and the whole scan is O(n) whatever the window size.
+fn find_marker(data: &[u8], window: usize) -> Option<usize> {
+    if window == 0 {
+        return Some(0);
+    }
+    let mut counts = [0u32; 256];
+    let mut distinct = 0;
+    for (i, byte) in data.iter().enumerate() {
+        counts[*byte as usize] += 1;
+        if counts[*byte as usize] == 1 {
+            distinct += 1;
+        }
+        if i >= window {
+            let leaving = data[i - window] as usize;
+            counts[leaving] -= 1;
+            if counts[leaving] == 0 {
+                distinct -= 1;
+            }
+        }
+        if distinct == window {
+            return Some(i + 1);
+        }
+    }
+    return None;
+}
 
-fn solve_a(input_filename: &str) -> ResultOrErr<usize> {
-    let input_string = load_input(input_filename)?;
-    let input: &str = input_string.split_whitespace().next().unwrap();
-    let chars: Vec<char> = input.chars().collect();
-    for i in 4..input.len() {
-        let slice = &chars[i - 4..i];
-        assert!(slice.len() == 4);
-        let mut seen: HashMap<char, bool> = HashMap::new();
-        for char in slice {
-            seen.insert(*char, true);
+// The original approach, a fresh HashMap for every window position, only kept as the baseline for --bench
+fn find_marker_hashmap(data: &[u8], window: usize) -> Option<usize> {
+    for i in window..=data.len() {
+        let mut seen: HashMap<u8, bool> = HashMap::new();
+        for byte in &data[i - window..i] {
+            seen.insert(*byte, true);
         }
-        if seen.len() == 4 {
-            return Ok(i);
+        if seen.len() == window {
+            return Some(i);
         }
     }
+    return None;
+}
+
+fn solve(input_filename: &str, window: usize) -> ResultOrErr<usize> {
+    let input_string = load_input(input_filename)?;
+    let input: &str = match input_string.split_whitespace().next() {
+        Some(x) => x,
+        None => return Err("Input is empty".to_string())
+    };
+    return match find_marker(input.as_bytes(), window) {
+        Some(x) => Ok(x),
+        None => Err(format!("No marker of {} different characters found", window))
+    };
+}
 
-    return Err("No start of packet found".to_string());
+fn solve_a(input_filename: &str) -> ResultOrErr<usize> {
+    return solve(input_filename, 4);
 }
 
 fn solve_b(input_filename: &str) -> ResultOrErr<usize> {
-    let input_string = load_input(input_filename)?;
-    let input: &str = input_string.split_whitespace().next().unwrap();
-    let chars: Vec<char> = input.chars().collect();
-    for i in 14..input.len() {
-        let slice = &chars[i - 14..i];
-        assert!(slice.len() == 14);
-        let mut seen: HashMap<char, bool> = HashMap::new();
-        for char in slice {
-            seen.insert(*char, true);
-        }
-        if seen.len() == 14 {
-            return Ok(i);
-        }
+    return solve(input_filename, 14);
+}
+
+// Seeded generator for the benchmark's streams
+struct XorShift {
+    state: u64
+}
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        return self.state;
     }
 
-    return Err("No start of message found".to_string());
+    fn below(&mut self, limit: usize) -> usize {
+        return (self.next() % limit as u64) as usize;
+    }
 }
 
+// A stream of random letters drawn from only window - 1 of them, so no marker can turn up until the one placed
+// at the very end. That makes every approach scan the whole stream.
+fn generate_stream(length: usize, window: usize, rng: &mut XorShift) -> Vec<u8> {
+    let marker: Vec<u8> = (0..window as u8).map(|offset| b'a' + offset).collect();
+    let mut data: Vec<u8> = (0..length.saturating_sub(window)).map(|_| b'a' + rng.below(window - 1) as u8).collect();
+    data.extend_from_slice(&marker);
+    return data;
+}
+
+// Times the count array and the HashMap per window on generated streams, for both the part A and part B window
+// sizes
+fn bench(megabytes_arg: &str) -> ResultOrErr<String> {
+    let megabytes = match megabytes_arg.parse::<usize>() {
+        Ok(x) => x,
+        Err(_) => return Err(format!("--bench takes a stream size in MB, got '{}'", megabytes_arg))
+    };
+    let mut rng = XorShift{state: 0x2022_1206};
+
+    let mut report: Vec<String> = Vec::new();
+    for window in [4, 14] {
+        let data = generate_stream(megabytes * 1024 * 1024, window, &mut rng);
+
+        let start = Instant::now();
+        let count_array = find_marker(&data, window);
+        let count_array_time = start.elapsed();
+
+        let start = Instant::now();
+        let hashmap = find_marker_hashmap(&data, window);
+        let hashmap_time = start.elapsed();
+
+        if count_array != hashmap {
+            return Err(format!("Window {}: count array found {:?} but HashMap found {:?}", window, count_array, hashmap));
+        }
+        let marker = match count_array {
+            Some(x) => x,
+            None => return Err(format!("Generated stream for window {} had no marker", window))
+        };
+        report.push(format!(
+            "{} MB stream, window {}: marker at {}, HashMap {:?}, count array {:?}, speedup {:.1}x",
+            megabytes, window, marker, hashmap_time, count_array_time,
+            hashmap_time.as_secs_f64() / count_array_time.as_secs_f64().max(f64::EPSILON)
+        ));
+    }
+
+    return Ok(report.join("\n"));
+}
 
 fn load_input(input_filename: &str) -> ResultOrErr<String> {
     return match fs::read_to_string(input_filename) {
@@ -67,3 +165,49 @@ fn load_input(input_filename: &str) -> ResultOrErr<String> {
         Err(x) => Err(x.to_string())
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The puzzle's example streams with their part A and part B markers
+    const EXAMPLES: [(&str, usize, usize); 5] = [
+        ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19),
+        ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+        ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+        ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29),
+        ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26)
+    ];
+
+    #[test]
+    fn finds_the_example_markers() {
+        for (stream, part_a, part_b) in EXAMPLES {
+            assert_eq!(find_marker(stream.as_bytes(), 4), Some(part_a), "{}", stream);
+            assert_eq!(find_marker(stream.as_bytes(), 14), Some(part_b), "{}", stream);
+            assert_eq!(find_marker_hashmap(stream.as_bytes(), 4), Some(part_a), "{}", stream);
+            assert_eq!(find_marker_hashmap(stream.as_bytes(), 14), Some(part_b), "{}", stream);
+        }
+    }
+
+    #[test]
+    fn small_windows() {
+        assert_eq!(find_marker(b"abc", 0), Some(0));
+        assert_eq!(find_marker(b"", 0), Some(0));
+        assert_eq!(find_marker(b"abc", 1), Some(1));
+        assert_eq!(find_marker(b"", 1), None);
+        assert_eq!(find_marker(b"aab", 2), Some(3));
+    }
+
+    #[test]
+    fn window_larger_than_the_input() {
+        assert_eq!(find_marker(b"abc", 4), None);
+        assert_eq!(find_marker(b"abcd", 4), Some(4));
+        assert_eq!(find_marker_hashmap(b"abc", 4), None);
+    }
+
+    #[test]
+    fn no_marker_when_letters_repeat() {
+        assert_eq!(find_marker(b"abcabcabc", 4), None);
+        assert_eq!(find_marker_hashmap(b"abcabcabc", 4), None);
+    }
+}